@@ -0,0 +1,194 @@
+/*
+ * thegrep - Tar Heel Extended Global Regular Expression Print
+ *
+ * Author(s): Alana Fiordalisi, Madison Huber
+ * ONYEN(s): fiordali, hubermm
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff and partner.
+ */
+
+/*
+ * ANSI colouring of output for --color, configured like GNU grep through
+ * the GREP_COLORS environment variable.
+ */
+
+/**
+ * The SGR sequence, such as "01;31", used for each part of the output,
+ * named after its GREP_COLORS capability. An empty sequence leaves that
+ * part uncoloured, and `erase` controls whether each coloured part is
+ * followed by an erase-to-end-of-line, which GNU grep's `ne` turns off.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colors {
+    pub selected_match: String,
+    pub context_match: String,
+    pub selected_line: String,
+    pub context_line: String,
+    pub filename: String,
+    pub line_number: String,
+    pub byte_offset: String,
+    pub separator: String,
+    pub erase: bool,
+}
+
+impl Default for Colors {
+    /**
+     * GNU grep's default colours.
+     */
+    fn default() -> Colors {
+        Colors {
+            selected_match: String::from("01;31"),
+            context_match: String::from("01;31"),
+            selected_line: String::new(),
+            context_line: String::new(),
+            filename: String::from("35"),
+            line_number: String::from("32"),
+            byte_offset: String::from("32"),
+            separator: String::from("36"),
+            erase: true,
+        }
+    }
+}
+
+impl Colors {
+    /**
+     * Colours that leave everything uncoloured.
+     */
+    pub fn none() -> Colors {
+        Colors {
+            selected_match: String::new(),
+            context_match: String::new(),
+            selected_line: String::new(),
+            context_line: String::new(),
+            filename: String::new(),
+            line_number: String::new(),
+            byte_offset: String::new(),
+            separator: String::new(),
+            erase: false,
+        }
+    }
+
+    /**
+     * The default colours overridden by a GREP_COLORS value, a
+     * colon-separated list such as "ms=01;31:fn=35:ne". `mt` sets both
+     * kinds of match, and unknown capabilities are ignored.
+     */
+    pub fn parse(spec: &str) -> Colors {
+        let mut colors = Colors::default();
+        for capability in spec.split(':') {
+            let (name, value) = match capability.find('=') {
+                Some(idx) => (&capability[..idx], capability[idx + 1..].to_string()),
+                None => (capability, String::new()),
+            };
+            match name {
+                "mt" => {
+                    colors.selected_match = value.clone();
+                    colors.context_match = value;
+                }
+                "ms" => colors.selected_match = value,
+                "mc" => colors.context_match = value,
+                "sl" => colors.selected_line = value,
+                "cx" => colors.context_line = value,
+                "fn" => colors.filename = value,
+                "ln" => colors.line_number = value,
+                "bn" => colors.byte_offset = value,
+                "se" => colors.separator = value,
+                "ne" => colors.erase = false,
+                _ => {}
+            }
+        }
+        colors
+    }
+
+    /**
+     * Wrap text in the given SGR sequence, unless either is empty.
+     */
+    pub fn paint(&self, sgr: &str, text: &str) -> String {
+        if sgr.is_empty() || text.is_empty() {
+            return text.to_string();
+        }
+        let erase = if self.erase { "\x1b[K" } else { "" };
+        format!("\x1b[{}m{}{}\x1b[m{}", sgr, erase, text, erase)
+    }
+
+    /**
+     * Paint a line whose matches lie at the given byte spans, in the
+     * colours of a selected line or of a context line.
+     */
+    pub fn paint_line(&self, text: &str, spans: &[(usize, usize)], selected: bool) -> String {
+        let (line, matched) = match selected {
+            true => (&self.selected_line, &self.selected_match),
+            false => (&self.context_line, &self.context_match),
+        };
+        let mut painted = String::new();
+        let mut pos = 0;
+        for (start, end) in spans.iter().filter(|(start, end)| start < end) {
+            painted += &self.paint(line, &text[pos..*start]);
+            painted += &self.paint(matched, &text[*start..*end]);
+            pos = *end;
+        }
+        painted += &self.paint(line, &text[pos..]);
+        painted
+    }
+}
+
+/**
+ * Decide the colours for a --color setting. With "auto", output is only
+ * coloured when stdout is a terminal and NO_COLOR is unset or empty.
+ */
+pub fn colors_for(when: &str) -> Colors {
+    let enabled = match when {
+        "always" => true,
+        "auto" => {
+            use std::io::IsTerminal;
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            !no_color && std::io::stdout().is_terminal()
+        }
+        _ => false,
+    };
+    match (enabled, std::env::var("GREP_COLORS")) {
+        (false, _) => Colors::none(),
+        (true, Ok(spec)) => Colors::parse(&spec),
+        (true, Err(_)) => Colors::default(),
+    }
+}
+
+#[cfg(test)]
+mod color_tests {
+    use super::*;
+
+    #[test]
+    fn paint() {
+        let colors = Colors::default();
+        assert_eq!(colors.paint("35", "f"), "\x1b[35m\x1b[Kf\x1b[m\x1b[K");
+        assert_eq!(colors.paint("", "f"), "f");
+        assert_eq!(colors.paint("35", ""), "");
+        assert_eq!(Colors::none().paint("35", "f"), "\x1b[35mf\x1b[m");
+    }
+
+    #[test]
+    fn parse() {
+        let colors = Colors::parse("mt=01;32:fn=:ne:xx=1");
+        assert_eq!(colors.selected_match, "01;32");
+        assert_eq!(colors.context_match, "01;32");
+        assert_eq!(colors.filename, "");
+        assert_eq!(colors.line_number, "32");
+        assert!(!colors.erase);
+    }
+
+    #[test]
+    fn paint_line() {
+        let colors = Colors::parse("ms=1:sl=2:ne");
+        assert_eq!(
+            colors.paint_line("abcd", &[(1, 2), (3, 3)], true),
+            "\x1b[2ma\x1b[m\x1b[1mb\x1b[m\x1b[2mcd\x1b[m"
+        );
+        assert_eq!(
+            colors.paint_line("abcd", &[(1, 2)], false),
+            "a\x1b[01;31mb\x1b[mcd"
+        );
+        assert_eq!(Colors::none().paint_line("abcd", &[(1, 2)], true), "abcd");
+    }
+}
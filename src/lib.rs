@@ -0,0 +1,26 @@
+/*
+ * thegrep - Tar Heel egrep
+ *
+ * Author(s): Alana Fiordalisi, Madison Huber
+ * ONYEN(s): fiordali, hubermm
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff and partner.
+ */
+
+/*
+ *
+ * thegrep – Tar Heel Extended Global Regular Expressions Print
+ *
+ * The regular expression engine behind the thegrep binary. Most users
+ * only need the `Regex` facade, while the tokenizer, parser and NFA
+ * stages remain public for tools that work with them directly.
+ *
+ */
+pub mod nfa;
+pub mod parser;
+pub mod regex;
+pub mod tokenizer;
+
+pub use self::regex::{Captures, Error, Match, Matches, Regex, RegexBuilder};
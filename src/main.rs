@@ -14,13 +14,37 @@
  * thegrep – Tar Heel Extended Global Regular Expressions Print
  *
  */
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
 extern crate structopt;
+use structopt::clap::ErrorKind;
 use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "thegrep", about = "Tar Heel egrep")]
 struct Options {
-    #[structopt(help = "Regular Expression Pattern")]
-    pattern: String,
+    #[structopt(
+        help = "Regular Expression Pattern",
+        raw(required_unless = r#""equiv""#)
+    )]
+    pattern: Option<String>,
+
+    #[structopt(
+        long = "equiv",
+        help = "Check whether two patterns accept exactly the same strings. This is an option rather than an `equiv` subcommand so that `thegrep equiv FILE` still searches for the word equiv",
+        raw(
+            value_names = r#"&["LHS", "RHS"]"#,
+            number_of_values = "2",
+            multiple = "false",
+            conflicts_with = r#""pattern""#
+        )
+    )]
+    equiv: Vec<String>,
+
+    #[structopt(
+        long = "subset",
+        help = "With --equiv, only check that LHS's strings are accepted by RHS",
+        raw(requires = r#""equiv""#)
+    )]
+    subset: bool,
 
     #[structopt(short = "p", long = "parse", help = "Show Parsed AST")]
     parse: bool,
@@ -31,6 +55,20 @@ struct Options {
     #[structopt(short = "d", long = "dot", help = "Produce dot representation of NFA")]
     dot: bool,
 
+    #[structopt(
+        long = "trace-input",
+        help = "Highlight the path of the given string through the dot representation",
+        raw(requires = r#""dot""#)
+    )]
+    trace_input: Option<String>,
+
+    #[structopt(
+        long = "export",
+        help = "Export the NFA (or the AST with -p) as json, mermaid, graphml or dot",
+        raw(possible_values = r#"&["json", "mermaid", "graphml", "dot"]"#)
+    )]
+    export: Option<Format>,
+
     #[structopt(
         short = "g",
         long = "gen",
@@ -39,25 +77,451 @@ struct Options {
     )]
     num: usize,
 
+    #[structopt(
+        long = "length",
+        help = "Generate strings uniformly among those with a length in a range such as 8..=12 (the end of 8..12 is excluded)"
+    )]
+    length: Option<Lengths>,
+
+    #[structopt(
+        long = "gen-negative",
+        help = "Generate near-miss strings that the pattern rejects",
+        default_value = "0"
+    )]
+    negatives: usize,
+
+    #[structopt(
+        long = "exact",
+        help = "Draw --gen-negative strings from every rejected string, not just near misses"
+    )]
+    exact: bool,
+
+    #[structopt(
+        long = "seed",
+        help = "Seed the random generator so --gen repeats its strings"
+    )]
+    seed: Option<u64>,
+
+    #[structopt(long = "max-len", help = "Generate strings of at most this many chars")]
+    max_len: Option<usize>,
+
+    #[structopt(long = "unique", help = "Generate each string at most once")]
+    unique: bool,
+
+    #[structopt(
+        long = "analyze",
+        help = "Report whether the language is empty or finite, and its shortest and longest strings"
+    )]
+    analyze: bool,
+
+    #[structopt(
+        long = "why-not",
+        help = "Explain how far the pattern got in the given string before failing to match"
+    )]
+    why_not: Option<String>,
+
+    #[structopt(
+        long = "coverage",
+        help = "Report how many input lines each alternative of the pattern matched on"
+    )]
+    coverage: bool,
+
+    #[structopt(
+        long = "cover-edges",
+        help = "Generate a small set of strings that take every transition of the NFA"
+    )]
+    cover_edges: bool,
+
+    #[structopt(
+        long = "enumerate",
+        help = "List every accepted string up to the given length, shortest first"
+    )]
+    enumerate: Option<usize>,
+
+    #[structopt(
+        long = "alphabet",
+        help = "The chars to build strings from (default: the chars in the pattern, plus one other)"
+    )]
+    alphabet: Option<String>,
+
+    #[structopt(
+        long = "count-lengths",
+        help = "Count the accepted strings of each length in a range such as 1..=20, over --alphabet or else every letter and digit plus the pattern's chars"
+    )]
+    count_lengths: Option<Lengths>,
+
+    #[structopt(short = "v", long = "invert-match", help = "Select non-matching lines")]
+    invert: bool,
+
+    #[structopt(
+        short = "x",
+        long = "line-regexp",
+        help = "Select only lines the pattern matches in full"
+    )]
+    line_regexp: bool,
+
+    #[structopt(
+        short = "w",
+        long = "word-regexp",
+        help = "Select only lines where the pattern matches a whole word"
+    )]
+    word_regexp: bool,
+
+    #[structopt(
+        short = "c",
+        long = "count",
+        help = "Print only a count of selected lines per file"
+    )]
+    count: bool,
+
+    #[structopt(
+        short = "m",
+        long = "max-count",
+        help = "Stop reading a file after NUM selected lines"
+    )]
+    max_count: Option<usize>,
+
+    #[structopt(
+        short = "q",
+        long = "quiet",
+        help = "Print nothing, and stop at the first selected line",
+        raw(alias = r#""silent""#)
+    )]
+    quiet: bool,
+
+    #[structopt(
+        short = "s",
+        long = "no-messages",
+        help = "Do not report files that are missing or cannot be read"
+    )]
+    no_messages: bool,
+
+    #[structopt(
+        short = "n",
+        long = "line-number",
+        help = "Prefix each line with its line number"
+    )]
+    line_number: bool,
+
+    #[structopt(
+        short = "b",
+        long = "byte-offset",
+        help = "Prefix each line with the byte offset it starts at"
+    )]
+    byte_offset: bool,
+
+    #[structopt(
+        long = "column",
+        help = "Prefix each line with the column its first match starts at"
+    )]
+    column: bool,
+
+    #[structopt(
+        long = "column-units",
+        help = "Count columns in bytes or in chars",
+        default_value = "bytes",
+        raw(possible_values = r#"&["bytes", "chars"]"#)
+    )]
+    column_units: String,
+
+    #[structopt(
+        short = "o",
+        long = "only-matching",
+        help = "Print only the non-empty matches, each on its own line"
+    )]
+    only_matching: bool,
+
+    #[structopt(
+        long = "vimgrep",
+        help = "Print a file:line:column:text record for every match"
+    )]
+    vimgrep: bool,
+
+    #[structopt(
+        short = "H",
+        long = "with-filename",
+        help = "Prefix each line with its file name",
+        raw(overrides_with = r#""no_filename""#)
+    )]
+    with_filename: bool,
+
+    #[structopt(
+        short = "h",
+        long = "no-filename",
+        help = "Never prefix lines with their file name",
+        raw(overrides_with = r#""with_filename""#)
+    )]
+    no_filename: bool,
+
+    #[structopt(
+        short = "l",
+        long = "files-with-matches",
+        help = "Print only the names of files with selected lines"
+    )]
+    files_with_matches: bool,
+
+    #[structopt(
+        short = "L",
+        long = "files-without-match",
+        help = "Print only the names of files without selected lines"
+    )]
+    files_without_match: bool,
+
+    #[structopt(
+        short = "Z",
+        long = "null",
+        help = "Follow file names with a NUL byte instead of the usual separator"
+    )]
+    null: bool,
+
+    #[structopt(long = "label", help = "The file name to give stdin")]
+    label: Option<String>,
+
+    #[structopt(
+        short = "A",
+        long = "after-context",
+        help = "Print NUM lines of context after each selected line"
+    )]
+    after_context: Option<usize>,
+
+    #[structopt(
+        short = "B",
+        long = "before-context",
+        help = "Print NUM lines of context before each selected line"
+    )]
+    before_context: Option<usize>,
+
+    #[structopt(
+        short = "C",
+        long = "context",
+        help = "Print NUM lines of context before and after each selected line"
+    )]
+    context: Option<usize>,
+
+    #[structopt(
+        long = "color",
+        help = "Highlight matches, file names, line numbers and separators (a bare --color means auto)",
+        raw(
+            possible_values = r#"&["auto", "always", "never"]"#,
+            alias = r#""colour""#,
+            require_equals = "true",
+            min_values = "0"
+        )
+    )]
+    color: Option<String>,
+
+    #[structopt(
+        long = "binary-files",
+        help = "Report matches in binary files, search them as text, or skip them",
+        default_value = "binary",
+        raw(possible_values = r#"&["binary", "text", "without-match"]"#)
+    )]
+    binary_files: String,
+
+    #[structopt(
+        short = "a",
+        long = "text",
+        help = "Search binary files as if they were text, like --binary-files=text"
+    )]
+    text: bool,
+
+    #[structopt(
+        short = "I",
+        help = "Treat binary files as having no matches, like --binary-files=without-match"
+    )]
+    skip_binary: bool,
+
+    #[structopt(
+        short = "r",
+        long = "recursive",
+        help = "Search directories recursively, following only symlinks named on the command line"
+    )]
+    recursive: bool,
+
+    #[structopt(
+        short = "R",
+        long = "dereference-recursive",
+        help = "Search directories recursively, following every symlink"
+    )]
+    dereference_recursive: bool,
+
+    #[structopt(
+        long = "include",
+        help = "Only search files whose names match GLOB",
+        raw(number_of_values = "1")
+    )]
+    include: Vec<String>,
+
+    #[structopt(
+        long = "exclude",
+        help = "Skip files whose names match GLOB",
+        raw(number_of_values = "1")
+    )]
+    exclude: Vec<String>,
+
+    #[structopt(
+        long = "exclude-dir",
+        help = "Skip directories whose names match GLOB when recursing",
+        raw(number_of_values = "1")
+    )]
+    exclude_dir: Vec<String>,
+
+    #[structopt(
+        long = "hidden",
+        help = "Search hidden files and directories when recursing"
+    )]
+    hidden: bool,
+
+    #[structopt(
+        long = "no-ignore",
+        help = "Do not honour .gitignore and .ignore files"
+    )]
+    no_ignore: bool,
+
     #[structopt(help = "FILES")]
     paths: Vec<String>,
 }
 
-pub mod tokenizer;
-use self::tokenizer::Tokenizer;
-pub mod parser;
-use self::parser::Parser;
+/**
+ * A range of string lengths, written as `N`, `A..B` or `A..=B`. As with
+ * Rust ranges, `A..B` excludes `B` and `A..=B` includes it.
+ */
+#[derive(Debug)]
+struct Lengths(std::ops::RangeInclusive<usize>);
+
+impl std::str::FromStr for Lengths {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Lengths, String> {
+        let number = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid length range: {}", s))
+        };
+        if let Some(idx) = s.find("..=") {
+            Ok(Lengths(number(&s[..idx])?..=number(&s[idx + 3..])?))
+        } else if let Some(idx) = s.find("..") {
+            let end = number(&s[idx + 2..])?;
+            if end == 0 {
+                return Err(format!("Empty length range: {}", s));
+            }
+            Ok(Lengths(number(&s[..idx])?..=end - 1))
+        } else {
+            let n = number(s)?;
+            Ok(Lengths(n..=n))
+        }
+    }
+}
+
+/**
+ * The formats that the AST and NFA can be exported to.
+ */
+#[derive(Debug)]
+enum Format {
+    Json,
+    Mermaid,
+    GraphML,
+    Dot,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "json" => Ok(Format::Json),
+            "mermaid" => Ok(Format::Mermaid),
+            "graphml" => Ok(Format::GraphML),
+            "dot" => Ok(Format::Dot),
+            _ => Err(format!("Unknown export format: {}", s)),
+        }
+    }
+}
 
-pub mod nfa;
-use self::nfa::helpers::gen;
-use self::nfa::helpers::nfa_dot;
-use self::nfa::NFA;
+use thegrep::nfa::helpers::analyze;
+use thegrep::nfa::helpers::edge_coverage;
+use thegrep::nfa::helpers::nfa_dot;
+use thegrep::nfa::helpers::nfa_dot_with_trace;
+use thegrep::nfa::helpers::PatternCoverage;
+use thegrep::nfa::helpers::{ast_dot, ast_graphml, ast_json, ast_mermaid};
+use thegrep::nfa::helpers::{count_lengths, default_alphabet, enumerate, gen_alphabet};
+use thegrep::nfa::helpers::{gen_negative, gen_negative_exact};
+use thegrep::nfa::helpers::{gen_uniform_with, gen_with, GenOptions};
+use thegrep::nfa::helpers::{nfa_graphml, nfa_json, nfa_mermaid};
+use thegrep::nfa::NFA;
+use thegrep::parser::Parser;
+use thegrep::tokenizer::Tokenizer;
+use thegrep::{Regex, RegexBuilder};
 
-// pub mod gen;
+mod color;
+mod walk;
+use color::{colors_for, Colors};
+use walk::{walk, WalkOptions};
 
 fn main() {
-    let opt = Options::from_args();
-    eval(&opt.pattern, &opt);
+    let opt = match Options::clap().get_matches_from_safe(std::env::args()) {
+        Ok(matches) => {
+            let mut opt = Options::from_clap(&matches);
+            // A bare --color means auto, while leaving it out means never
+            if matches.is_present("color") && opt.color.is_none() {
+                opt.color = Some(String::from("auto"));
+            }
+            opt
+        }
+        Err(e) => match e.kind {
+            ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed => e.exit(),
+            // Usage errors are errors like any other, not a failure to match
+            _ => {
+                eprintln!("{}", e.message);
+                std::process::exit(2);
+            }
+        },
+    };
+    // --gen defaults to 0, so clap cannot tell whether it was given
+    if opt.length.is_some() && opt.num == 0 {
+        eprintln!("error: The argument '--length <length>' requires '--gen <num>'");
+        std::process::exit(2);
+    }
+    match (opt.equiv.as_slice(), &opt.pattern) {
+        ([lhs, rhs], _) => eval_equiv(lhs, rhs, opt.subset),
+        (_, Some(pattern)) => eval(pattern, &opt),
+        (_, None) => unreachable!("a pattern is required without --equiv"),
+    }
+}
+
+/**
+ * Print "equivalent" (or "subset") when the patterns pass the check,
+ * otherwise print the shortest string that tells them apart.
+ */
+fn eval_equiv(lhs_pattern: &str, rhs_pattern: &str, subset: bool) {
+    let (lhs, rhs) = match (NFA::from(lhs_pattern), NFA::from(rhs_pattern)) {
+        (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+        (Err(msg), _) | (_, Err(msg)) => {
+            eprintln!("thegrep: {}", msg);
+            std::process::exit(2);
+        }
+    };
+
+    let counterexample = if subset {
+        lhs.to_dfa().difference(&rhs.to_dfa()).shortest_accepted()
+    } else {
+        lhs.counterexample(&rhs)
+    };
+
+    match counterexample {
+        None if subset => println!("subset"),
+        None => println!("equivalent"),
+        Some(s) => {
+            let (by, not_by) = if lhs.accepts_exactly(&s) {
+                (lhs_pattern, rhs_pattern)
+            } else {
+                (rhs_pattern, lhs_pattern)
+            };
+            println!("{:?} is accepted by {:?} but not by {:?}", s, by, not_by);
+            std::process::exit(1);
+        }
+    }
+    std::process::exit(0);
 }
 
 fn eval(input: &str, options: &Options) {
@@ -65,39 +529,106 @@ fn eval(input: &str, options: &Options) {
         eval_show_tokens(input);
     }
 
+    if let Some(format) = &options.export {
+        eval_export(input, format, options.parse);
+        std::process::exit(0);
+    }
+
     if options.parse {
         eval_show_parse(input);
     }
 
     if options.dot {
-        let nfa = NFA::from(input).unwrap();
-        println!("{}", nfa_dot(&nfa));
+        let nfa = compile(input);
+        match &options.trace_input {
+            Some(trace) => println!("{}", nfa_dot_with_trace(&nfa, trace)),
+            None => println!("{}", nfa_dot(&nfa)),
+        }
+        std::process::exit(0);
+    }
+
+    if options.analyze {
+        eval_analyze(input);
+        std::process::exit(0);
+    }
+
+    if options.cover_edges {
+        eval_cover_edges(input);
+        std::process::exit(0);
+    }
+
+    if let Some(max_len) = options.enumerate {
+        eval_enumerate(input, options, max_len);
+        std::process::exit(0);
+    }
+
+    if let Some(lengths) = &options.count_lengths {
+        eval_count_lengths(input, options, lengths);
         std::process::exit(0);
     }
 
-    if options.num > 0 {
-        let nfa = NFA::from(input).unwrap();
-        let strings = gen(&nfa, options.num);
-        for string in strings {
-            println!("{}", string);
+    if options.num > 0 || options.negatives > 0 {
+        eval_gen(input, options);
+        std::process::exit(0);
+    }
+
+    let regex = RegexBuilder::new(input)
+        .whole_line(options.line_regexp)
+        .whole_word(options.word_regexp)
+        .build();
+    let regex = match regex {
+        Ok(regex) => regex,
+        Err(e) => {
+            eprintln!("thegrep: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(line) = &options.why_not {
+        eval_why_not(&regex, line, options);
+    }
+
+    if options.coverage {
+        if let Err(e) = eval_coverage(regex, options) {
+            eprintln!("thegrep: {}", e);
+            std::process::exit(2);
         }
         std::process::exit(0);
     }
 
-    let mut input_mod = String::from(".*");
-    input_mod.push_str(input);
-    let nfa = NFA::from(&input_mod).unwrap();
-    let result = if options.paths.len() > 0 {
-        eval_files(&options, &nfa)
+    let mut out = Output {
+        colors: colors_for(options.color.as_deref().unwrap_or("never")),
+        printed_lines: false,
+        found: false,
+        failed: false,
+    };
+    if !options.paths.is_empty() || recursive(options) {
+        eval_files(options, &regex, &mut out);
     } else {
-        eval_stdin(&nfa)
+        eval_stdin(options, &regex, &mut out);
+    }
+
+    // As in GNU grep, a line found with -q outweighs any errors
+    let status = match (out.found, out.failed) {
+        (true, _) if options.quiet => 0,
+        (_, true) => 2,
+        (true, false) => 0,
+        (false, false) => 1,
     };
+    std::process::exit(status);
+}
 
-    if let Err(e) = result {
-        eprintln!("{}", e);
-    }
+/**
+ * Compile the pattern to an NFA, or exit with status 2 if it is invalid.
+ */
+fn compile(input: &str) -> NFA {
+    NFA::from(input).unwrap_or_else(|msg| {
+        eprintln!("thegrep: {}", msg);
+        std::process::exit(2);
+    })
 }
 
+#[allow(clippy::while_let_on_iterator)]
 fn eval_show_tokens(input: &str) {
     let mut tokens = Tokenizer::new(input);
     while let Some(token) = tokens.next() {
@@ -114,31 +645,590 @@ fn eval_show_parse(input: &str) {
     }
 }
 
+fn eval_analyze(input: &str) {
+    let analysis = analyze(&compile(input));
+    println!("empty: {}", analysis.empty);
+    println!("finite: {}", analysis.finite);
+    match analysis.shortest {
+        Some(s) => println!("shortest: {:?}", s),
+        None => println!("shortest: none"),
+    }
+    match analysis.longest {
+        Some(s) => println!("longest: {:?}", s),
+        None if analysis.empty => println!("longest: none"),
+        None => println!("longest: unbounded"),
+    }
+}
+
+fn eval_cover_edges(input: &str) {
+    let coverage = edge_coverage(&compile(input));
+    for string in coverage.strings {
+        println!("{}", string);
+    }
+    for (from, to) in coverage.unreachable {
+        eprintln!("thegrep: unreachable transition {} -> {}", from, to);
+    }
+}
+
+/**
+ * Print the longest partial match of the pattern in the line, with a
+ * caret where it broke off and the chars that would have let it go on.
+ */
+fn eval_why_not(regex: &Regex, line: &str, options: &Options) {
+    let input = regex.as_str();
+    let diagnosis = match regex.why_not(line) {
+        Some(diagnosis) => diagnosis,
+        None => {
+            println!("{:?} matches {:?}", line, input);
+            std::process::exit(0);
+        }
+    };
+
+    let column = |offset: usize| line[..offset].chars().count();
+    let (start, position) = (column(diagnosis.start), column(diagnosis.position));
+    println!("{:?} does not match {:?}", line, input);
+    println!("  {}", line);
+    println!("  {}{}^", " ".repeat(start), "~".repeat(position - start));
+    let found = match line[diagnosis.position..].chars().next() {
+        Some(c) => format!("found {:?}", c),
+        None => String::from("found the end of the line"),
+    };
+    // Nothing is expected when only a match that -x or -w ruled out was left
+    let expected = if !diagnosis.expected.is_empty() {
+        diagnosis.expected.join(" or ")
+    } else if options.word_regexp && !options.line_regexp {
+        String::from("the end of a word")
+    } else {
+        String::from("the end of the line")
+    };
+    println!("expected {}, {}", expected, found);
+    std::process::exit(1);
+}
+
+fn eval_gen(input: &str, options: &Options) {
+    let nfa = compile(input);
+    let gen_options = GenOptions {
+        seed: options.seed,
+        max_len: options.max_len,
+        unique: options.unique,
+        alphabet: options
+            .alphabet
+            .as_ref()
+            .map(|chars| chars.chars().collect()),
+    };
+    let strings = match &options.length {
+        _ if options.negatives > 0 && options.exact => {
+            gen_negative_exact(&nfa, options.negatives, &gen_options)
+        }
+        _ if options.negatives > 0 => gen_negative(&nfa, options.negatives, &gen_options),
+        Some(lengths) => {
+            match gen_uniform_with(&nfa, lengths.0.clone(), options.num, &gen_options) {
+                Ok(strings) => strings,
+                Err(msg) => {
+                    eprintln!("thegrep: {}", msg);
+                    std::process::exit(2);
+                }
+            }
+        }
+        None => gen_with(&nfa, options.num, &gen_options),
+    };
+    for string in strings {
+        println!("{}", string);
+    }
+}
+
+fn eval_enumerate(input: &str, options: &Options, max_len: usize) {
+    let nfa = compile(input);
+    let alphabet = alphabet(&nfa, options);
+    for string in enumerate(&nfa, &alphabet, max_len) {
+        println!("{}", string);
+    }
+}
+
+fn eval_count_lengths(input: &str, options: &Options, lengths: &Lengths) {
+    let nfa = compile(input);
+    let alphabet: Vec<char> = match &options.alphabet {
+        Some(chars) => chars.chars().collect(),
+        None => gen_alphabet(&nfa),
+    };
+    let counts = match count_lengths(&nfa, &alphabet, *lengths.0.end()) {
+        Ok(counts) => counts,
+        Err(msg) => {
+            eprintln!("thegrep: {}", msg);
+            std::process::exit(2);
+        }
+    };
+    let alphabet: String = alphabet.into_iter().collect();
+    println!("alphabet\t{}", alphabet.escape_debug());
+    println!("length\tcount");
+    for len in lengths.0.clone() {
+        match counts[len] {
+            Some(count) => println!("{}\t{}", len, count),
+            None => println!("{}\t>= 2^128", len),
+        }
+    }
+}
+
+/**
+ * The chars given with --alphabet, or else the pattern's own alphabet.
+ */
+fn alphabet(nfa: &NFA, options: &Options) -> Vec<char> {
+    match &options.alphabet {
+        Some(chars) => chars.chars().collect(),
+        None => default_alphabet(nfa),
+    }
+}
+
+fn eval_export(input: &str, format: &Format, ast: bool) {
+    let exported = if ast {
+        Parser::parse(Tokenizer::new(input)).map(|ast| match format {
+            Format::Json => ast_json(&ast),
+            Format::Mermaid => ast_mermaid(&ast),
+            Format::GraphML => ast_graphml(&ast),
+            Format::Dot => ast_dot(&ast),
+        })
+    } else {
+        NFA::from(input).map(|nfa| match format {
+            Format::Json => nfa_json(&nfa),
+            Format::Mermaid => nfa_mermaid(&nfa),
+            Format::GraphML => nfa_graphml(&nfa),
+            Format::Dot => nfa_dot(&nfa),
+        })
+    };
+    match exported {
+        Ok(exported) => println!("{}", exported),
+        Err(msg) => {
+            eprintln!("thegrep: {}", msg);
+            std::process::exit(2);
+        }
+    }
+}
+
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
 
-fn eval_files(opt: &Options, nfa: &NFA) -> io::Result<()> {
+/**
+ * Search each of the files given, reporting those that cannot be read
+ * and going on with the rest. A file named `-` is stdin.
+ */
+fn eval_files(opt: &Options, regex: &Regex, out: &mut Output) {
+    if recursive(opt) {
+        return eval_tree(opt, regex, out);
+    }
+    for path in opt.paths.iter() {
+        if opt.quiet && out.found {
+            break;
+        }
+        if path == "-" {
+            eval_stdin(opt, regex, out);
+            continue;
+        }
+        let result = File::open(path)
+            .and_then(|file| eval_lines(io::BufReader::new(file), Some(path), opt, regex, out));
+        if let Err(e) = result {
+            report(&e, Some(path), opt, out);
+        }
+    }
+}
+
+/**
+ * Note that an input could not be searched, and say why unless -s was
+ * given.
+ */
+fn report(e: &io::Error, name: Option<&str>, opt: &Options, out: &mut Output) {
+    out.failed = true;
+    if !opt.no_messages {
+        match name {
+            Some(name) => eprintln!("thegrep: {}: {}", name, e),
+            None => eprintln!("thegrep: {}", e),
+        }
+    }
+}
+
+/**
+ * How binary files are handled, where -a and -I take precedence over
+ * --binary-files.
+ */
+fn binary_files(opt: &Options) -> &str {
+    match (opt.text, opt.skip_binary) {
+        (true, _) => "text",
+        (false, true) => "without-match",
+        (false, false) => &opt.binary_files,
+    }
+}
+
+/**
+ * Whether -r or -R was given.
+ */
+fn recursive(opt: &Options) -> bool {
+    opt.recursive || opt.dereference_recursive
+}
+
+/**
+ * Search every file under the given paths, or under the current
+ * directory when none are given, in which case the names are printed
+ * without a leading "./". A path named `-` is stdin.
+ */
+fn eval_tree(opt: &Options, regex: &Regex, out: &mut Output) {
+    let walk_options = WalkOptions {
+        follow_links: opt.dereference_recursive,
+        hidden: opt.hidden,
+        ignore_files: !opt.no_ignore,
+        include: opt.include.clone(),
+        exclude: opt.exclude.clone(),
+        exclude_dir: opt.exclude_dir.clone(),
+    };
+    let here = vec![String::from(".")];
+    let paths = if opt.paths.is_empty() {
+        &here
+    } else {
+        &opt.paths
+    };
+
+    walk(paths, &walk_options, &mut |entry| {
+        if opt.quiet && out.found {
+            return;
+        }
+        let path = match entry {
+            Ok(path) => path,
+            Err(e) => return report(&e, None, opt, out),
+        };
+        if path.as_os_str() == "-" {
+            return eval_stdin(opt, regex, out);
+        }
+        let name = path.to_string_lossy();
+        let name = match opt.paths.is_empty() {
+            true => name.strip_prefix("./").unwrap_or(&name),
+            false => &name,
+        };
+        let result = File::open(&path)
+            .and_then(|file| eval_lines(io::BufReader::new(file), Some(name), opt, regex, out));
+        if let Err(e) = result {
+            report(&e, Some(name), opt, out);
+        }
+    });
+}
+
+/**
+ * Run the pattern over every line of the input and print, under the
+ * pattern, how many lines each of its optional parts matched on.
+ */
+fn eval_coverage(regex: Regex, opt: &Options) -> io::Result<()> {
+    let input = String::from(regex.as_str());
+    let mut coverage = PatternCoverage::new(regex);
+
+    if opt.paths.is_empty() {
+        for line in io::stdin().lock().lines() {
+            coverage.add_line(&line?);
+        }
+    }
     for path in opt.paths.iter() {
-        let file = File::open(path)?;
-        let reader = io::BufReader::new(file);
-        eval_lines(reader, nfa)?;
+        for line in io::BufReader::new(File::open(path)?).lines() {
+            coverage.add_line(&line?);
+        }
+    }
+
+    println!("{} lines, {} matched", coverage.lines(), coverage.matched());
+    let width = coverage.lines().to_string().len();
+    println!("{:>width$}  {}", "", input, width = width);
+    for part in coverage.parts() {
+        println!(
+            "{:>width$}  {}{}{}",
+            part.lines,
+            " ".repeat(part.span.start),
+            "^".repeat(part.span.len()),
+            if part.lines == 0 {
+                "  never matched"
+            } else {
+                ""
+            },
+            width = width
+        );
     }
     Ok(())
 }
 
-fn eval_stdin(nfa: &NFA) -> io::Result<()> {
+/**
+ * The 1-based column of a byte offset into a line, in the units chosen
+ * with --column-units.
+ */
+fn column(line: &str, start: usize, opt: &Options) -> usize {
+    match opt.column_units.as_str() {
+        "chars" => line[..start].chars().count() + 1,
+        _ => start + 1,
+    }
+}
+
+fn eval_stdin(opt: &Options, regex: &Regex, out: &mut Output) {
     let stdin = io::stdin();
     let reader = stdin.lock();
-    eval_lines(reader, nfa)
+    if let Err(e) = eval_lines(reader, None, opt, regex, out) {
+        report(&e, None, opt, out);
+    }
+}
+
+/**
+ * The state of the output shared by all the inputs searched: how it is
+ * coloured, whether any lines have been printed yet, and what the exit
+ * status will be. An input is found when it has a selected line, or with
+ * -L when it has none, and failed when it could not be read.
+ */
+#[derive(Debug)]
+struct Output {
+    colors: Colors,
+    printed_lines: bool,
+    found: bool,
+    failed: bool,
+}
+
+/**
+ * A line read from an input, with its 1-based number and the byte offset
+ * it starts at.
+ */
+struct Line {
+    number: usize,
+    offset: usize,
+    text: String,
 }
 
-fn eval_lines<R: BufRead>(reader: R, nfa: &NFA) -> io::Result<()> {
-    for line_result in reader.lines() {
-        let line = line_result?;
-        if nfa.accepts(&line) {
-            println!("{}", line);
+/**
+ * Print the selected lines of one input, which are the lines that match
+ * or, with -v, the lines that do not. With -c only their number is
+ * printed, and with -m the input is read no further than the last
+ * selected line allowed and its trailing context. Lines before a
+ * selected line are kept in a ring buffer until it is known whether
+ * they are needed for its context.
+ *
+ * An input is binary if its first block holds a NUL byte, or from the
+ * first line that holds one or is not valid UTF-8. Once it is, the first
+ * selected line is reported with a single message rather than printed,
+ * unless --binary-files says to treat the input as text or skip it.
+ */
+fn eval_lines<R: BufRead>(
+    mut reader: R,
+    path: Option<&str>,
+    opt: &Options,
+    regex: &Regex,
+    out: &mut Output,
+) -> io::Result<()> {
+    let name = path.unwrap_or(match &opt.label {
+        Some(label) => label.as_str(),
+        None => "(standard input)",
+    });
+    let listing = opt.files_with_matches || opt.files_without_match;
+    let (before, after) = context(opt);
+    let binary_files = binary_files(opt);
+    let mut binary = binary_files != "text" && reader.fill_buf()?.contains(&0);
+
+    let mut history: VecDeque<Line> = VecDeque::with_capacity(before);
+    let mut last_printed: Option<usize> = None;
+    let mut trailing = 0;
+    let mut selected = 0;
+    let mut line_number = 0;
+    let mut offset = 0;
+    let mut buf = Vec::new();
+    loop {
+        let done = opt.max_count == Some(selected) || (listing && selected > 0);
+        if done && (trailing == 0 || opt.count || listing) {
+            break;
+        }
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        if binary_files != "text" && (buf.contains(&0) || std::str::from_utf8(&buf).is_err()) {
+            // nothing more is printed from a binary input, not even context
+            binary = true;
+            trailing = 0;
+        }
+        if binary && binary_files == "without-match" {
+            selected = 0;
+            break;
+        }
+        line_number += 1;
+        let decoded = String::from_utf8_lossy(&buf);
+        let text = decoded.strip_suffix('\n').unwrap_or(&decoded);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let line = Line {
+            number: line_number,
+            offset,
+            text: text.to_string(),
+        };
+        offset += read;
+
+        let first = if done { None } else { regex.find(text) };
+        if !done && first.is_some() != opt.invert {
+            selected += 1;
+            if opt.quiet {
+                out.found = true;
+                return Ok(());
+            } else if listing || opt.count {
+                continue;
+            } else if binary {
+                println!("Binary file {} matches", name);
+                break;
+            } else if opt.vimgrep {
+                let starts: Vec<usize> = match first {
+                    Some(_) => regex.find_iter(text).map(|m| m.start()).collect(),
+                    None => vec![0],
+                };
+                for start in starts {
+                    let column = column(text, start, opt);
+                    println!("{}:{}:{}:{}", name, line_number, column, text);
+                }
+            } else if opt.only_matching {
+                // find_iter steps past empty matches, which are not printed
+                let matches = regex.find_iter(text).filter(|m| m.start() < m.end());
+                for m in matches.take_while(|_| !opt.invert) {
+                    let only = Line {
+                        number: line_number,
+                        offset: line.offset + m.start(),
+                        text: m.as_str().to_string(),
+                    };
+                    let column = column(text, m.start(), opt);
+                    print_line(&only, name, Some(column), true, opt, regex, out);
+                }
+            } else {
+                // a group of lines not adjacent to the last one printed is
+                // set off from it by a separator when there is context
+                let start = history.front().map_or(line_number, |line| line.number);
+                let adjacent = last_printed.is_some_and(|last| last + 1 == start);
+                if (before > 0 || after > 0) && out.printed_lines && !adjacent {
+                    println!("{}", out.colors.paint(&out.colors.separator, "--"));
+                }
+                for context in history.drain(..) {
+                    print_line(&context, name, None, false, opt, regex, out);
+                }
+                let column = first.map(|m| column(text, m.start(), opt));
+                print_line(&line, name, column, true, opt, regex, out);
+                out.printed_lines = true;
+                last_printed = Some(line_number);
+                trailing = after;
+            }
+        } else if trailing > 0 {
+            print_line(&line, name, None, false, opt, regex, out);
+            last_printed = Some(line_number);
+            trailing -= 1;
+        } else if before > 0 {
+            if history.len() == before {
+                history.pop_front();
+            }
+            history.push_back(line);
+        }
+    }
+
+    out.found |= (selected > 0) != opt.files_without_match;
+    if opt.quiet {
+        return Ok(());
+    }
+    let colors = &out.colors;
+    if listing {
+        if (selected > 0) == opt.files_with_matches {
+            let end = if opt.null { '\0' } else { '\n' };
+            print!("{}{}", colors.paint(&colors.filename, name), end);
+        }
+    } else if opt.count {
+        match with_filename(opt) {
+            true => println!(
+                "{}{}{}",
+                colors.paint(&colors.filename, name),
+                file_separator(':', opt, colors),
+                selected
+            ),
+            false => println!("{}", selected),
         }
     }
     Ok(())
 }
+
+/**
+ * Print a line with the prefixes asked for, each followed by a separator,
+ * which is `:` for selected lines and `-` for context lines. The matches
+ * are highlighted in selected lines, or in context lines with -v.
+ */
+fn print_line(
+    line: &Line,
+    name: &str,
+    column: Option<usize>,
+    selected: bool,
+    opt: &Options,
+    regex: &Regex,
+    out: &Output,
+) {
+    let colors = &out.colors;
+    let sep = if selected { ':' } else { '-' };
+    let painted_sep = colors.paint(&colors.separator, &sep.to_string());
+
+    let mut prefix = String::new();
+    if with_filename(opt) {
+        prefix += &colors.paint(&colors.filename, name);
+        prefix += &file_separator(sep, opt, colors);
+    }
+    if opt.line_number {
+        prefix += &colors.paint(&colors.line_number, &line.number.to_string());
+        prefix += &painted_sep;
+    }
+    if let (true, Some(column)) = (opt.column, column) {
+        prefix += &colors.paint(&colors.line_number, &column.to_string());
+        prefix += &painted_sep;
+    }
+    if opt.byte_offset {
+        prefix += &colors.paint(&colors.byte_offset, &line.offset.to_string());
+        prefix += &painted_sep;
+    }
+
+    let spans: Vec<(usize, usize)> = match (*colors == Colors::none(), selected != opt.invert) {
+        (false, true) if opt.only_matching => vec![(0, line.text.len())],
+        (false, true) => regex
+            .find_iter(&line.text)
+            .map(|m| (m.start(), m.end()))
+            .collect(),
+        _ => vec![],
+    };
+    println!(
+        "{}{}",
+        prefix,
+        colors.paint_line(&line.text, &spans, selected)
+    );
+}
+
+/**
+ * The separator that follows a file name, which is a NUL byte with -Z.
+ */
+fn file_separator(sep: char, opt: &Options, colors: &Colors) -> String {
+    match opt.null {
+        true => String::from("\0"),
+        false => colors.paint(&colors.separator, &sep.to_string()),
+    }
+}
+
+/**
+ * The number of lines of context to print before and after each selected
+ * line, where -A and -B take precedence over -C. There is no context
+ * with -o, which prints matches rather than lines.
+ */
+fn context(opt: &Options) -> (usize, usize) {
+    if opt.only_matching {
+        return (0, 0);
+    }
+    let both = opt.context.unwrap_or(0);
+    (
+        opt.before_context.unwrap_or(both),
+        opt.after_context.unwrap_or(both),
+    )
+}
+
+/**
+ * Whether lines are prefixed with their file name, which by default
+ * they are only when several files are searched.
+ */
+fn with_filename(opt: &Options) -> bool {
+    if opt.no_filename {
+        false
+    } else {
+        opt.with_filename || opt.paths.len() > 1 || recursive(opt)
+    }
+}
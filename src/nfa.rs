@@ -1,5 +1,7 @@
+pub mod dfa;
 pub mod helpers;
-use std::ops::Add;
+use self::dfa::DFA;
+use std::ops::{Add, BitAnd, Not, Sub};
 
 // Starter code for PS06 - thegrep
 use self::State::*;
@@ -19,6 +21,7 @@ use self::State::*;
  * thegrep - Tar Heel Extended Global Regular Expressions Print
  *
  */
+#[allow(clippy::useless_attribute, clippy::empty_line_after_doc_comments)]
 use super::parser::Parser;
 use super::parser::AST;
 use super::tokenizer::Tokenizer;
@@ -31,6 +34,7 @@ use super::tokenizer::Tokenizer;
  * An NFA is represented by an arena Vec of States
  * and a start state.
  */
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Debug)]
 pub struct NFA {
     start: StateId,
@@ -42,13 +46,20 @@ impl NFA {
      * Construct an NFA from a regular expression pattern.
      */
     pub fn from(regular_expression: &str) -> Result<NFA, String> {
+        // Parse the Abstract Syntax Tree of the Regular Expression
+        let ast = &Parser::parse(Tokenizer::new(regular_expression))?;
+        Ok(NFA::from_ast(ast))
+    }
+
+    /**
+     * Construct an NFA from an already parsed Abstract Syntax Tree.
+     */
+    pub fn from_ast(ast: &AST) -> NFA {
         let mut nfa = NFA::new();
 
         let start = nfa.add_state(Start(None));
         nfa.start = start;
 
-        // Parse the Abstract Syntax Tree of the Regular Expression
-        let ast = &Parser::parse(Tokenizer::new(regular_expression))?;
         // The "body" of the NFA is made of the states between Start and End
         let body = nfa.gen_fragment(ast);
         nfa.join(nfa.start, body.start);
@@ -56,13 +67,14 @@ impl NFA {
         let end = nfa.add_state(End);
         nfa.join_fragment(&body, end);
 
-        Ok(nfa)
+        nfa
     }
 
     /**
      * Given an input string, simulate the NFA to determine if the
      * input is accepted by the input string.
      */
+    #[allow(clippy::while_let_on_iterator, clippy::collapsible_match)]
     pub fn accepts(&self, input: &str) -> bool {
         let mut itr = input.chars();
 
@@ -90,12 +102,9 @@ impl NFA {
                         self.find_next(rhs, &mut next_states);
                         self.find_next(lhs, &mut next_states);
                     }
-                    Match(Char::Any, Some(idx)) => {
-                        self.find_next(idx, &mut next_states);
-                    }
-                    Match(Char::Literal(c), Some(idx)) => {
+                    Match(ref c, Some(idx)) => {
                         // If char in input matches char in Match, call helper
-                        if c == curr {
+                        if c.matches(curr) {
                             self.find_next(idx, &mut next_states);
                         }
                     }
@@ -129,6 +138,191 @@ impl NFA {
         false
     }
 
+    /**
+     * Given an input string, simulate the NFA to determine if the
+     * entire input, rather than just a prefix of it, is accepted.
+     */
+    pub fn accepts_exactly(&self, input: &str) -> bool {
+        let end = self.states.len() - 1;
+        let mut threads = Vec::new();
+        self.add_thread(self.start, 0, &mut threads);
+        for c in input.chars() {
+            let mut next_threads = Vec::new();
+            for (state, _) in threads {
+                if let Match(m, Some(next)) = &self.states[state] {
+                    if m.matches(c) {
+                        self.add_thread(*next, 0, &mut next_threads);
+                    }
+                }
+            }
+            threads = next_threads;
+        }
+        threads.iter().any(|&(state, _)| state == end)
+    }
+
+    /**
+     * Determinize the NFA by subset construction.
+     */
+    pub fn to_dfa(&self) -> DFA {
+        DFA::from_nfa(self)
+    }
+
+    /**
+     * An NFA accepting exactly the strings both NFAs accept.
+     */
+    pub fn intersection(&self, other: &NFA) -> NFA {
+        self.to_dfa().intersection(&other.to_dfa()).to_nfa()
+    }
+
+    /**
+     * An NFA accepting exactly the strings this NFA rejects.
+     */
+    pub fn complement(&self) -> NFA {
+        self.to_dfa().complement().to_nfa()
+    }
+
+    /**
+     * An NFA accepting exactly the strings this NFA accepts and the
+     * other NFA rejects.
+     */
+    pub fn difference(&self, other: &NFA) -> NFA {
+        self.to_dfa().difference(&other.to_dfa()).to_nfa()
+    }
+
+    /**
+     * Whether every string this NFA accepts is also accepted by the other.
+     */
+    pub fn is_subset_of(&self, other: &NFA) -> bool {
+        self.to_dfa()
+            .difference(&other.to_dfa())
+            .shortest_accepted()
+            .is_none()
+    }
+
+    /**
+     * Whether both NFAs accept exactly the same strings.
+     */
+    pub fn equivalent(&self, other: &NFA) -> bool {
+        self.counterexample(other).is_none()
+    }
+
+    /**
+     * The shortest string accepted by one of the NFAs but not the other,
+     * or None if they are equivalent.
+     */
+    pub fn counterexample(&self, other: &NFA) -> Option<String> {
+        self.to_dfa()
+            .symmetric_difference(&other.to_dfa())
+            .shortest_accepted()
+    }
+
+    /**
+     * Search the input, beginning at byte offset `from`, for the leftmost
+     * substring accepted by the NFA, preferring the longest one among
+     * those starting at the same position. The match is returned as a
+     * pair of byte offsets (start, end).
+     */
+    pub fn find_at(&self, input: &str, from: usize) -> Option<(usize, usize)> {
+        self.find_at_bounded(input, from, |_| true, |_| true)
+    }
+
+    /**
+     * Like `find_at`, but a match may only begin at a byte offset for which
+     * `starts` holds and only end at one for which `ends` holds. A match
+     * that would end elsewhere gives way to a shorter one from the same
+     * position, or failing that to one beginning further on.
+     */
+    pub fn find_at_bounded<S, E>(
+        &self,
+        input: &str,
+        from: usize,
+        starts: S,
+        ends: E,
+    ) -> Option<(usize, usize)>
+    where
+        S: Fn(usize) -> bool,
+        E: Fn(usize) -> bool,
+    {
+        let end = self.states.len() - 1;
+        let mut best: Option<(usize, usize)> = None;
+
+        // Each thread is a state paired with the offset its match began at
+        let mut threads: Vec<(StateId, usize)> = Vec::new();
+        let mut chars = input[from..].char_indices();
+
+        loop {
+            let (pos, c) = match chars.next() {
+                Some((idx, c)) => (from + idx, Some(c)),
+                None => (input.len(), None),
+            };
+
+            // New threads may only begin until a match has been found
+            if best.is_none() && starts(pos) {
+                self.add_thread(self.start, pos, &mut threads);
+            }
+
+            // Threads that have reached End are candidate matches
+            for &(state, origin) in &threads {
+                if state == end && ends(pos) {
+                    best = match best {
+                        Some((s, _)) if s < origin => best,
+                        _ => Some((origin, pos)),
+                    };
+                }
+            }
+
+            // Only threads that could beat the best match are worth running
+            if let Some((s, _)) = best {
+                threads.retain(|&(_, origin)| origin <= s);
+            }
+
+            let c = match c {
+                Some(c) if !threads.is_empty() || best.is_none() => c,
+                _ => break,
+            };
+
+            let mut next_threads = Vec::new();
+            for (state, origin) in threads {
+                if let Match(m, Some(next)) = &self.states[state] {
+                    if m.matches(c) {
+                        self.add_thread(*next, origin, &mut next_threads);
+                    }
+                }
+            }
+            threads = next_threads;
+        }
+
+        best
+    }
+
+    /**
+     * Add the Match and End states reachable by epsilon transitions from
+     * a state to a list of threads, keeping the earliest origin when a
+     * state is reached more than once.
+     */
+    fn add_thread(&self, state: StateId, origin: usize, threads: &mut Vec<(StateId, usize)>) {
+        let mut stack = vec![state];
+        let mut seen = vec![false; self.states.len()];
+        while let Some(id) = stack.pop() {
+            if seen[id] {
+                continue;
+            }
+            seen[id] = true;
+            match self.states[id] {
+                Start(Some(next)) => stack.push(next),
+                Split(Some(lhs), Some(rhs)) => {
+                    stack.push(rhs);
+                    stack.push(lhs);
+                }
+                Match(_, Some(_)) | End => match threads.iter_mut().find(|(s, _)| *s == id) {
+                    Some(thread) => thread.1 = thread.1.min(origin),
+                    None => threads.push((id, origin)),
+                },
+                _ => {}
+            }
+        }
+    }
+
     /**
      * Given a current StateId, find all possible next states
      * from that State.
@@ -165,6 +359,7 @@ impl NFA {
  * Unit tests for `accepts` method.
  */
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod accepts_tests {
     use super::*;
 
@@ -381,6 +576,81 @@ mod accepts_tests {
     }
 }
 
+/**
+ * Unit tests for `find_at` method.
+ */
+#[cfg(test)]
+mod find_tests {
+    use super::*;
+
+    #[test]
+    fn find_literal() {
+        let nfa = NFA::from("bc").unwrap();
+        assert_eq!(nfa.find_at("abcd", 0), Some((1, 3)));
+    }
+
+    #[test]
+    fn find_none() {
+        let nfa = NFA::from("bc").unwrap();
+        assert_eq!(nfa.find_at("acbd", 0), None);
+    }
+
+    #[test]
+    fn find_from_offset() {
+        let nfa = NFA::from("a").unwrap();
+        assert_eq!(nfa.find_at("aba", 1), Some((2, 3)));
+    }
+
+    #[test]
+    fn find_leftmost() {
+        let nfa = NFA::from("abcd|c").unwrap();
+        assert_eq!(nfa.find_at("abcd", 0), Some((0, 4)));
+    }
+
+    #[test]
+    fn find_longest() {
+        let nfa = NFA::from("a|ab|abc").unwrap();
+        assert_eq!(nfa.find_at("xabcx", 0), Some((1, 4)));
+    }
+
+    #[test]
+    fn find_closure_longest() {
+        let nfa = NFA::from("ba*").unwrap();
+        assert_eq!(nfa.find_at("cbaaac", 0), Some((1, 5)));
+    }
+
+    #[test]
+    fn find_empty() {
+        let nfa = NFA::from("a*").unwrap();
+        assert_eq!(nfa.find_at("bbb", 0), Some((0, 0)));
+        assert_eq!(nfa.find_at("", 0), Some((0, 0)));
+    }
+
+    #[test]
+    fn find_multibyte() {
+        let nfa = NFA::from("é.").unwrap();
+        assert_eq!(nfa.find_at("caféx", 0), Some((3, 6)));
+    }
+
+    #[test]
+    fn find_bounded() {
+        let nfa = NFA::from("ab*").unwrap();
+        let found = nfa.find_at_bounded("abbab", 0, |_| true, |end| end == 2);
+        assert_eq!(found, Some((0, 2)));
+        let found = nfa.find_at_bounded("abbab", 0, |start| start > 0, |_| true);
+        assert_eq!(found, Some((3, 5)));
+        let found = nfa.find_at_bounded("abbab", 0, |start| start == 0, |end| end == 5);
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn find_nested_closure() {
+        let nfa = NFA::from("(a*)*b").unwrap();
+        assert_eq!(nfa.find_at("xaab", 0), Some((1, 4)));
+        assert_eq!(nfa.find_at("xaa", 0), None);
+    }
+}
+
 /**
  * ===== Internal API =====
  */
@@ -404,11 +674,67 @@ enum State {
 /**
  * Chars are the matching label of a non-epsilon edge in the
  * transition diagram representation of the NFA.
+ * - Literal matches exactly one char
+ * - Any matches every char
+ * - Except matches every char but the listed ones
  */
 #[derive(Debug, Clone)]
 enum Char {
     Literal(char),
     Any,
+    Except(Vec<char>),
+}
+
+impl Char {
+    /**
+     * Whether this label permits a transition on the given char.
+     */
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Char::Literal(l) => *l == c,
+            Char::Any => true,
+            Char::Except(excluded) => !excluded.contains(&c),
+        }
+    }
+
+    /**
+     * A predictable char this label permits, preferring letters and
+     * digits so that example strings stay readable.
+     */
+    fn example(&self) -> char {
+        match self {
+            Char::Literal(c) => *c,
+            _ => ('a'..='z')
+                .chain('A'..='Z')
+                .chain('0'..='9')
+                .chain((0..=std::char::MAX as u32).filter_map(std::char::from_u32))
+                .find(|c| self.matches(*c))
+                .unwrap_or('?'),
+        }
+    }
+}
+
+/**
+ * Extend the marked states to every state with an edge into a marked
+ * state until nothing changes. Starting from the accepting states of
+ * an automaton this finds its live states.
+ */
+fn mark_live<F, I>(mut live: Vec<bool>, successors: F) -> Vec<bool>
+where
+    F: Fn(StateId) -> I,
+    I: IntoIterator<Item = StateId>,
+{
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for id in 0..live.len() {
+            if !live[id] && successors(id).into_iter().any(|next| live[next]) {
+                live[id] = true;
+                changed = true;
+            }
+        }
+    }
+    live
 }
 
 /**
@@ -457,6 +783,9 @@ impl NFA {
             AST::Alternation(lhs, rhs) => self.gen_alt(lhs, rhs),
             AST::Closure(c) => self.gen_closure(c),
             AST::OneOrMore(c) => self.gen_plus(c),
+            AST::Group(_, c) => self.gen_fragment(c),
+            AST::Intersection(lhs, rhs) => self.gen_intersection(lhs, rhs),
+            AST::Complement(c) => self.gen_complement(c),
         }
     }
 
@@ -492,6 +821,7 @@ impl NFA {
      * and concatenates them (left to right). Returns
      * corresponding Fragment.
      */
+    #[allow(clippy::borrowed_box, clippy::needless_borrow)]
     fn gen_cat(&mut self, lhs: &Box<AST>, rhs: &Box<AST>) -> Fragment {
         let left = self.gen_fragment(&lhs);
         let right = self.gen_fragment(&rhs);
@@ -508,6 +838,7 @@ impl NFA {
      * and creates a Split state that connects to both.
      * Returns corresponding Fragment.
      */
+    #[allow(clippy::borrowed_box, clippy::needless_borrow)]
     fn gen_alt(&mut self, lhs: &Box<AST>, rhs: &Box<AST>) -> Fragment {
         let left = self.gen_fragment(&lhs);
         let right = self.gen_fragment(&rhs);
@@ -532,6 +863,7 @@ impl NFA {
      * Split state that connects to child and has an
      * unconnected arm. Returns corresponding Fragment.
      */
+    #[allow(clippy::borrowed_box, clippy::needless_borrow)]
     fn gen_closure(&mut self, c: &Box<AST>) -> Fragment {
         let child = self.gen_fragment(&c);
         let split = self.add_state(Split(Some(child.start), None));
@@ -542,6 +874,7 @@ impl NFA {
         }
     }
 
+    #[allow(clippy::borrowed_box, clippy::needless_borrow, clippy::single_match)]
     fn gen_plus(&mut self, c: &Box<AST>) -> Fragment {
         let child = self.gen_fragment(&c);
         let split = self.add_state(Split(Some(child.start), None));
@@ -556,6 +889,54 @@ impl NFA {
         }
     }
 
+    /**
+     * Helper for gen_fragment AST::Intersection
+     * Thompson fragments cannot intersect, so both sides are built
+     * as NFAs of their own, determinized and combined by the product
+     * construction. The resulting DFA is embedded as the Fragment.
+     */
+    fn gen_intersection(&mut self, lhs: &AST, rhs: &AST) -> Fragment {
+        let left = NFA::from_ast(lhs).to_dfa();
+        let right = NFA::from_ast(rhs).to_dfa();
+        left.intersection(&right).embed(self)
+    }
+
+    /**
+     * Helper for gen_fragment AST::Complement
+     * The child is built as an NFA of its own and determinized so that
+     * its accepting states can be flipped. The resulting DFA is embedded
+     * as the Fragment.
+     */
+    fn gen_complement(&mut self, c: &AST) -> Fragment {
+        NFA::from_ast(c).to_dfa().complement().embed(self)
+    }
+
+    /**
+     * Creates a Fragment that accepts nothing at all: a Match state
+     * that only ever loops back to itself, so End is never reached.
+     */
+    fn gen_nothing(&mut self) -> Fragment {
+        let state = self.add_state(Match(Char::Any, None));
+        self.join(state, state);
+        Fragment {
+            start: state,
+            ends: vec![],
+        }
+    }
+
+    /**
+     * Creates a Fragment that accepts only the empty string: a Split
+     * whose bound arm accepts nothing and whose other arm is unjoined.
+     */
+    fn gen_empty(&mut self) -> Fragment {
+        let nothing = self.gen_nothing();
+        let split = self.add_state(Split(Some(nothing.start), None));
+        Fragment {
+            start: split,
+            ends: vec![split],
+        }
+    }
+
     /**
      * Join all the loose ends of a fragment to another StateId.
      */
@@ -583,6 +964,7 @@ impl NFA {
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_format)]
 mod fragment_tests {
     use super::*;
     use crate::nfa::helpers::nfa_dot;
@@ -634,6 +1016,7 @@ mod fragment_tests {
  * of the two original NFAs.
  */
 
+#[allow(clippy::empty_line_after_doc_comments)]
 impl Add for NFA {
     type Output = NFA;
     fn add(self, rhs: NFA) -> NFA {
@@ -700,6 +1083,7 @@ impl Add for NFA {
  * Using references permits reuse of original NFAs later.
  */
 
+#[allow(clippy::empty_line_after_doc_comments)]
 impl Add<&NFA> for &NFA {
     type Output = NFA;
     fn add(self, rhs: &NFA) -> NFA {
@@ -762,6 +1146,7 @@ impl Add<&NFA> for &NFA {
 #[cfg(test)]
 mod add_tests {
     use super::*;
+    #[allow(unused_imports)]
     use crate::nfa::helpers::nfa_dot;
 
     #[test]
@@ -832,3 +1217,254 @@ mod add_tests {
         assert!(nfa_cat.accepts("bdd"));
     }
 }
+
+/**
+ * Override the & operator so that when it is applied to two NFAs,
+ * the output is an NFA accepting the intersection of their languages.
+ */
+impl BitAnd for NFA {
+    type Output = NFA;
+    fn bitand(self, rhs: NFA) -> NFA {
+        self.intersection(&rhs)
+    }
+}
+
+impl BitAnd<&NFA> for &NFA {
+    type Output = NFA;
+    fn bitand(self, rhs: &NFA) -> NFA {
+        self.intersection(rhs)
+    }
+}
+
+/**
+ * Override the ! operator so that when it is applied to an NFA,
+ * the output is an NFA accepting the complement of its language.
+ */
+impl Not for NFA {
+    type Output = NFA;
+    fn not(self) -> NFA {
+        self.complement()
+    }
+}
+
+impl Not for &NFA {
+    type Output = NFA;
+    fn not(self) -> NFA {
+        self.complement()
+    }
+}
+
+/**
+ * Override the - operator so that when it is applied to two NFAs,
+ * the output is an NFA accepting the strings of the lhs's language
+ * that are not in the rhs's language.
+ */
+impl Sub for NFA {
+    type Output = NFA;
+    fn sub(self, rhs: NFA) -> NFA {
+        self.difference(&rhs)
+    }
+}
+
+impl Sub<&NFA> for &NFA {
+    type Output = NFA;
+    fn sub(self, rhs: &NFA) -> NFA {
+        self.difference(rhs)
+    }
+}
+
+#[cfg(test)]
+mod bool_syntax_tests {
+    use super::*;
+    use crate::nfa::helpers::gen;
+
+    #[test]
+    fn intersection_syntax() {
+        let nfa = NFA::from(".*error.*&~(.*debug.*)").unwrap();
+        assert!(nfa.accepts_exactly("an error occurred"));
+        assert!(!nfa.accepts_exactly("debug: an error occurred"));
+        assert!(!nfa.accepts_exactly("all good"));
+    }
+
+    #[test]
+    fn intersection_in_catenation() {
+        let nfa = NFA::from("x(a*&(aa)*)y").unwrap();
+        assert!(nfa.accepts_exactly("xy"));
+        assert!(nfa.accepts_exactly("xaay"));
+        assert!(!nfa.accepts_exactly("xay"));
+        assert!(!nfa.accepts_exactly("xaaay"));
+    }
+
+    #[test]
+    fn complement_in_closure() {
+        let nfa = NFA::from("(~(.*b.*)c)+").unwrap();
+        assert!(nfa.accepts_exactly("c"));
+        assert!(nfa.accepts_exactly("aacxc"));
+        assert!(!nfa.accepts_exactly("abc"));
+    }
+
+    #[test]
+    fn complement_of_nonempty_is_empty_string() {
+        let nfa = NFA::from("a~(.+)b").unwrap();
+        assert!(nfa.accepts_exactly("ab"));
+        assert!(!nfa.accepts_exactly("axb"));
+        for s in gen(&nfa, 10) {
+            assert_eq!(s, "ab");
+        }
+    }
+
+    #[test]
+    fn empty_intersection() {
+        let nfa = NFA::from("a(b&c)|d").unwrap();
+        assert!(!nfa.accepts_exactly("ab"));
+        assert!(nfa.accepts_exactly("d"));
+        for s in gen(&nfa, 10) {
+            assert_eq!(s, "d");
+        }
+    }
+
+    #[test]
+    fn gen_intersection_syntax() {
+        let nfa = NFA::from("(a|b|c)+&.*a.*&~(.*c.*)").unwrap();
+        for s in gen(&nfa, 20) {
+            assert!(nfa.accepts_exactly(&s));
+            assert!(s.contains('a'));
+            assert!(!s.contains('c'));
+        }
+    }
+}
+
+#[cfg(test)]
+mod equiv_tests {
+    use super::*;
+
+    #[test]
+    fn equivalent_rewrites() {
+        let a = NFA::from("(a|b)*").unwrap();
+        let b = NFA::from("(a*b*)*").unwrap();
+        assert!(a.equivalent(&b));
+        assert!(b.equivalent(&a));
+        assert_eq!(a.counterexample(&b), None);
+    }
+
+    #[test]
+    fn equivalent_plus() {
+        let a = NFA::from("aa*").unwrap();
+        let b = NFA::from("a+").unwrap();
+        assert!(a.equivalent(&b));
+    }
+
+    #[test]
+    fn not_equivalent() {
+        let a = NFA::from("a*").unwrap();
+        let b = NFA::from("a+").unwrap();
+        assert!(!a.equivalent(&b));
+        assert_eq!(a.counterexample(&b), Some(String::new()));
+    }
+
+    #[test]
+    fn shortest_counterexample() {
+        let a = NFA::from("(ab|ba)*").unwrap();
+        let b = NFA::from("(ab)*(ba)*").unwrap();
+        assert_eq!(a.counterexample(&b), Some(String::from("baab")));
+    }
+
+    #[test]
+    fn counterexample_any() {
+        let a = NFA::from(".").unwrap();
+        let b = NFA::from("x|y").unwrap();
+        assert_eq!(a.counterexample(&b), Some(String::from("a")));
+    }
+
+    #[test]
+    fn subset() {
+        let a = NFA::from("ab+").unwrap();
+        let b = NFA::from("a.*").unwrap();
+        assert!(a.is_subset_of(&b));
+        assert!(!b.is_subset_of(&a));
+        assert!(a.is_subset_of(&a));
+    }
+}
+
+#[cfg(test)]
+mod bool_op_tests {
+    use super::*;
+    use crate::nfa::helpers::gen;
+
+    #[test]
+    fn accepts_exactly() {
+        let nfa = NFA::from("ab*").unwrap();
+        assert!(nfa.accepts_exactly("abbb"));
+        assert!(nfa.accepts("abbbc"));
+        assert!(!nfa.accepts_exactly("abbbc"));
+        assert!(!nfa.accepts_exactly(""));
+    }
+
+    #[test]
+    fn intersection_op() {
+        let nfa = NFA::from("a.*").unwrap() & NFA::from(".*b").unwrap();
+        assert!(nfa.accepts_exactly("ab"));
+        assert!(nfa.accepts_exactly("acccb"));
+        assert!(!nfa.accepts_exactly("acc"));
+        assert!(!nfa.accepts_exactly("ccb"));
+    }
+
+    #[test]
+    fn complement_op() {
+        let nfa = !NFA::from("a*").unwrap();
+        assert!(!nfa.accepts_exactly(""));
+        assert!(!nfa.accepts_exactly("aaa"));
+        assert!(nfa.accepts_exactly("b"));
+        assert!(nfa.accepts_exactly("aab"));
+    }
+
+    #[test]
+    fn difference_op() {
+        let nfa = NFA::from("(a|b)+").unwrap() - NFA::from("a+").unwrap();
+        assert!(nfa.accepts_exactly("b"));
+        assert!(nfa.accepts_exactly("aab"));
+        assert!(!nfa.accepts_exactly("aa"));
+        assert!(!nfa.accepts_exactly("c"));
+    }
+
+    #[test]
+    fn ops_on_refs() {
+        let a = NFA::from("(a|b)*c").unwrap();
+        let b = NFA::from("a*c").unwrap();
+        assert!((&a & &b).accepts_exactly("aac"));
+        assert!((&a - &b).accepts_exactly("bac"));
+        assert!((!&b).accepts_exactly("bac"));
+        assert!(a.accepts_exactly("bac"));
+    }
+
+    #[test]
+    fn gen_difference() {
+        let a = NFA::from("(a|b|.)*c").unwrap();
+        let b = NFA::from("a*(c|bc)").unwrap();
+        let diff = &a - &b;
+        for s in gen(&diff, 50) {
+            assert!(a.accepts_exactly(&s));
+            assert!(!b.accepts_exactly(&s));
+        }
+    }
+
+    #[test]
+    fn gen_difference_any() {
+        let a = NFA::from("..*").unwrap();
+        let b = NFA::from("x.*").unwrap();
+        let diff = &a - &b;
+        for s in gen(&diff, 50) {
+            assert!(a.accepts_exactly(&s));
+            assert!(!b.accepts_exactly(&s));
+        }
+    }
+
+    #[test]
+    fn gen_empty_difference() {
+        let a = NFA::from("ab").unwrap();
+        let diff = &a - &a;
+        assert!(!diff.accepts(""));
+        assert!(!diff.accepts("ab"));
+        assert!(gen(&diff, 5).is_empty());
+    }
+}
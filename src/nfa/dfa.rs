@@ -0,0 +1,628 @@
+use super::Char;
+use super::State::*;
+use super::StateId;
+use super::{mark_live, Fragment, NFA};
+use std::collections::HashMap;
+use std::ops::{BitAnd, Not, Sub};
+
+/*
+ * Deterministic finite automata, built from NFAs by subset construction,
+ * support the boolean language operations that Thompson fragments cannot
+ * express directly.
+ */
+
+/**
+ * A DFA is represented by a complete transition table over a finite
+ * alphabet of the chars its patterns name, plus one extra symbol that
+ * stands for every other char. State 0 is the start state.
+ */
+#[derive(Debug, Clone)]
+pub struct DFA {
+    alphabet: Vec<char>,
+    transitions: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+}
+
+impl DFA {
+    /**
+     * Determinize an NFA by subset construction. The empty subset is kept
+     * as a dead state so that every state has a transition on every symbol.
+     */
+    pub fn from_nfa(nfa: &NFA) -> DFA {
+        let alphabet = nfa_alphabet(nfa);
+        let end = nfa.states.len() - 1;
+
+        let mut dfa = DFA {
+            alphabet,
+            transitions: Vec::new(),
+            accepting: Vec::new(),
+        };
+
+        let mut ids: HashMap<Vec<StateId>, usize> = HashMap::new();
+        let mut subsets: Vec<Vec<StateId>> = Vec::new();
+
+        let start = closure(nfa, &[nfa.start]);
+        ids.insert(start.clone(), 0);
+        subsets.push(start);
+
+        let mut idx = 0;
+        while idx < subsets.len() {
+            let subset = subsets[idx].clone();
+            let mut row = Vec::with_capacity(dfa.symbols());
+            for symbol in 0..dfa.symbols() {
+                let c = dfa.alphabet.get(symbol).cloned();
+                let targets: Vec<StateId> = subset
+                    .iter()
+                    .filter_map(|&state| match &nfa.states[state] {
+                        Match(m, Some(next)) if symbol_matches(m, c) => Some(*next),
+                        _ => None,
+                    })
+                    .collect();
+                let next = closure(nfa, &targets);
+                let id = match ids.get(&next) {
+                    Some(id) => *id,
+                    None => {
+                        ids.insert(next.clone(), subsets.len());
+                        subsets.push(next);
+                        subsets.len() - 1
+                    }
+                };
+                row.push(id);
+            }
+            dfa.transitions.push(row);
+            dfa.accepting.push(subset.contains(&end));
+            idx += 1;
+        }
+
+        dfa
+    }
+
+    /**
+     * Convert the DFA back into an NFA. Only states that can still reach
+     * an accepting state are kept, so every path through the NFA can be
+     * completed into an accepted string.
+     */
+    pub fn to_nfa(&self) -> NFA {
+        let mut nfa = NFA::new();
+
+        let start = nfa.add_state(Start(None));
+        nfa.start = start;
+
+        let body = self.embed(&mut nfa);
+        nfa.join(nfa.start, body.start);
+
+        let end = nfa.add_state(End);
+        nfa.join_fragment(&body, end);
+
+        nfa
+    }
+
+    /**
+     * The number of states in the DFA.
+     */
+    pub fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    /**
+     * The state reached from state q on reading the char c.
+     */
+    pub(super) fn step(&self, q: usize, c: char) -> usize {
+        self.transitions[q][self.symbol(c)]
+    }
+
+    /**
+     * Whether state q is accepting.
+     */
+    pub(super) fn is_accepting(&self, q: usize) -> bool {
+        self.accepting[q]
+    }
+
+    /**
+     * One char for each symbol of the DFA, in order, which together
+     * cover every distinct way the DFA can read a char.
+     */
+    pub(super) fn sample_alphabet(&self) -> Vec<char> {
+        self.symbol_order().into_iter().map(|(c, _)| c).collect()
+    }
+
+    /**
+     * Given an input string, run the DFA to determine if some prefix
+     * of the input is accepted, mirroring `NFA::accepts`.
+     */
+    pub fn accepts(&self, input: &str) -> bool {
+        let mut state = 0;
+        if self.accepting[state] {
+            return true;
+        }
+        for c in input.chars() {
+            state = self.transitions[state][self.symbol(c)];
+            if self.accepting[state] {
+                return true;
+            }
+        }
+        false
+    }
+
+    /**
+     * Given an input string, run the DFA to determine if the entire
+     * input is accepted.
+     */
+    pub fn accepts_exactly(&self, input: &str) -> bool {
+        let state = input
+            .chars()
+            .fold(0, |state, c| self.transitions[state][self.symbol(c)]);
+        self.accepting[state]
+    }
+
+    /**
+     * A DFA accepting exactly the strings both DFAs accept.
+     */
+    pub fn intersection(&self, other: &DFA) -> DFA {
+        self.product(other, |lhs, rhs| lhs && rhs)
+    }
+
+    /**
+     * A DFA accepting exactly the strings this DFA rejects.
+     */
+    pub fn complement(&self) -> DFA {
+        let mut dfa = self.clone();
+        for accepting in dfa.accepting.iter_mut() {
+            *accepting = !*accepting;
+        }
+        dfa
+    }
+
+    /**
+     * A DFA accepting exactly the strings this DFA accepts and the
+     * other DFA rejects.
+     */
+    pub fn difference(&self, other: &DFA) -> DFA {
+        self.product(other, |lhs, rhs| lhs && !rhs)
+    }
+
+    /**
+     * A DFA accepting exactly the strings accepted by one DFA but not
+     * by the other.
+     */
+    pub fn symmetric_difference(&self, other: &DFA) -> DFA {
+        self.product(other, |lhs, rhs| lhs != rhs)
+    }
+
+    /**
+     * The shortest string the DFA accepts, breaking ties by choosing the
+     * alphabetically first, or None if it accepts nothing at all. This
+     * is a breadth first search from the start state.
+     */
+    pub fn shortest_accepted(&self) -> Option<String> {
+        let order = self.symbol_order();
+        let mut parents: Vec<Option<(usize, char)>> = vec![None; self.len()];
+        let mut seen = vec![false; self.len()];
+        let mut queue = std::collections::VecDeque::new();
+        seen[0] = true;
+        queue.push_back(0);
+
+        while let Some(q) = queue.pop_front() {
+            if self.accepting[q] {
+                // Walk the parents back to the start to spell the string
+                let mut chars = Vec::new();
+                let mut state = q;
+                while let Some((parent, c)) = parents[state] {
+                    chars.push(c);
+                    state = parent;
+                }
+                return Some(chars.iter().rev().collect());
+            }
+            for &(c, symbol) in &order {
+                let next = self.transitions[q][symbol];
+                if !seen[next] {
+                    seen[next] = true;
+                    parents[next] = Some((q, c));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/**
+ * Override the &, ! and - operators for DFAs in the same way they are
+ * overridden for NFAs.
+ */
+impl BitAnd for DFA {
+    type Output = DFA;
+    fn bitand(self, rhs: DFA) -> DFA {
+        self.intersection(&rhs)
+    }
+}
+
+impl BitAnd<&DFA> for &DFA {
+    type Output = DFA;
+    fn bitand(self, rhs: &DFA) -> DFA {
+        self.intersection(rhs)
+    }
+}
+
+impl Not for DFA {
+    type Output = DFA;
+    fn not(self) -> DFA {
+        self.complement()
+    }
+}
+
+impl Not for &DFA {
+    type Output = DFA;
+    fn not(self) -> DFA {
+        self.complement()
+    }
+}
+
+impl Sub for DFA {
+    type Output = DFA;
+    fn sub(self, rhs: DFA) -> DFA {
+        self.difference(&rhs)
+    }
+}
+
+impl Sub<&DFA> for &DFA {
+    type Output = DFA;
+    fn sub(self, rhs: &DFA) -> DFA {
+        self.difference(rhs)
+    }
+}
+
+/*
+ * ===== Internal API =====
+ */
+
+impl DFA {
+    /**
+     * The number of symbols, including the one for every other char.
+     */
+    fn symbols(&self) -> usize {
+        self.alphabet.len() + 1
+    }
+
+    /**
+     * The symbol a char is read as.
+     */
+    fn symbol(&self, c: char) -> usize {
+        match self.alphabet.binary_search(&c) {
+            Ok(idx) => idx,
+            Err(_) => self.alphabet.len(),
+        }
+    }
+
+    /**
+     * Every symbol paired with a char that reads as it, sorted by char.
+     * The "every other char" symbol is represented by the first letter
+     * or digit missing from the alphabet, falling back to the first
+     * char of all that is missing from it.
+     */
+    fn symbol_order(&self) -> Vec<(char, usize)> {
+        let other = Char::Except(self.alphabet.clone()).example();
+        let mut order: Vec<(char, usize)> = self
+            .alphabet
+            .iter()
+            .enumerate()
+            .map(|(symbol, c)| (*c, symbol))
+            .collect();
+        order.push((other, self.alphabet.len()));
+        order.sort();
+        order
+    }
+
+    /**
+     * Re-express the DFA over a larger alphabet. Chars the DFA did not
+     * name behave like its "every other char" symbol.
+     */
+    fn with_alphabet(&self, alphabet: &[char]) -> DFA {
+        let map: Vec<usize> = alphabet
+            .iter()
+            .map(|c| self.symbol(*c))
+            .chain(std::iter::once(self.alphabet.len()))
+            .collect();
+        DFA {
+            alphabet: alphabet.to_vec(),
+            transitions: self
+                .transitions
+                .iter()
+                .map(|row| map.iter().map(|symbol| row[*symbol]).collect())
+                .collect(),
+            accepting: self.accepting.clone(),
+        }
+    }
+
+    /**
+     * The product construction: run both DFAs in lockstep and decide
+     * whether each pair of states accepts from whether each half does.
+     */
+    fn product(&self, other: &DFA, accept: fn(bool, bool) -> bool) -> DFA {
+        let mut alphabet: Vec<char> = self.alphabet.clone();
+        alphabet.extend(other.alphabet.iter());
+        alphabet.sort();
+        alphabet.dedup();
+        let lhs = self.with_alphabet(&alphabet);
+        let rhs = other.with_alphabet(&alphabet);
+
+        let mut dfa = DFA {
+            alphabet,
+            transitions: Vec::new(),
+            accepting: Vec::new(),
+        };
+        let mut ids: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut pairs: Vec<(usize, usize)> = vec![(0, 0)];
+        ids.insert((0, 0), 0);
+
+        let mut idx = 0;
+        while idx < pairs.len() {
+            let (l, r) = pairs[idx];
+            let mut row = Vec::with_capacity(dfa.symbols());
+            for symbol in 0..dfa.symbols() {
+                let next = (lhs.transitions[l][symbol], rhs.transitions[r][symbol]);
+                let id = match ids.get(&next) {
+                    Some(id) => *id,
+                    None => {
+                        ids.insert(next, pairs.len());
+                        pairs.push(next);
+                        pairs.len() - 1
+                    }
+                };
+                row.push(id);
+            }
+            dfa.transitions.push(row);
+            dfa.accepting
+                .push(accept(lhs.accepting[l], rhs.accepting[r]));
+            idx += 1;
+        }
+
+        dfa
+    }
+
+    /**
+     * Which states can reach an accepting state.
+     */
+    pub(super) fn live(&self) -> Vec<bool> {
+        mark_live(self.accepting.clone(), |q| {
+            self.transitions[q].iter().cloned()
+        })
+    }
+
+    /**
+     * Add the live states of the DFA to an NFA under construction and
+     * return the Fragment they make up. Each DFA state becomes a chain
+     * of Splits over its outgoing Match states, and accepting is an
+     * unjoined end of the Fragment.
+     */
+    pub(super) fn embed(&self, nfa: &mut NFA) -> Fragment {
+        let live = self.live();
+        if !live[0] {
+            return nfa.gen_nothing();
+        }
+
+        // The entry state of each live DFA state, or None when the entry
+        // is simply to accept, along with the Match states whose targets
+        // must be bound once every entry is known
+        let mut entries: Vec<Option<StateId>> = vec![None; self.len()];
+        let mut pending: Vec<(StateId, usize)> = Vec::new();
+        let mut ends: Vec<StateId> = Vec::new();
+
+        for q in (0..self.len()).filter(|q| live[*q]) {
+            let mut options: Vec<StateId> = Vec::new();
+            for (label, target) in self.edges(q, &live) {
+                let state = nfa.add_state(Match(label, None));
+                pending.push((state, target));
+                options.push(state);
+            }
+
+            // Chain the options together with Splits, leaving the last
+            // Split's rhs unjoined when the state accepts
+            let mut entry = match options.pop() {
+                Some(last) if self.accepting[q] => {
+                    let split = nfa.add_state(Split(Some(last), None));
+                    ends.push(split);
+                    Some(split)
+                }
+                last => last,
+            };
+            while let Some(option) = options.pop() {
+                entry = Some(nfa.add_state(Split(Some(option), entry)));
+            }
+            entries[q] = entry;
+        }
+
+        for (state, target) in pending {
+            match entries[target] {
+                Some(entry) => nfa.join(state, entry),
+                None => ends.push(state),
+            }
+        }
+
+        match entries[0] {
+            Some(start) => Fragment { start, ends },
+            None => nfa.gen_empty(),
+        }
+    }
+
+    /**
+     * The labelled edges out of a state into live states, one per target.
+     * A target reached on the "every other char" symbol gets a single
+     * Except label, while any other target gets a Literal per char.
+     */
+    fn edges(&self, q: usize, live: &[bool]) -> Vec<(Char, usize)> {
+        let row = &self.transitions[q];
+        let other = row[self.alphabet.len()];
+        let mut edges = Vec::new();
+        if live[other] {
+            let excluded: Vec<char> = (0..self.alphabet.len())
+                .filter(|symbol| row[*symbol] != other)
+                .map(|symbol| self.alphabet[symbol])
+                .collect();
+            edges.push((except(excluded), other));
+        }
+        for (symbol, c) in self.alphabet.iter().enumerate() {
+            if row[symbol] != other && live[row[symbol]] {
+                edges.push((Char::Literal(*c), row[symbol]));
+            }
+        }
+        edges
+    }
+}
+
+/**
+ * An Except label, which is simply Any when nothing is excluded.
+ */
+fn except(excluded: Vec<char>) -> Char {
+    if excluded.is_empty() {
+        Char::Any
+    } else {
+        Char::Except(excluded)
+    }
+}
+
+/**
+ * The sorted chars named by an NFA's Match labels.
+ */
+fn nfa_alphabet(nfa: &NFA) -> Vec<char> {
+    let mut alphabet = Vec::new();
+    for state in nfa.states.iter() {
+        match state {
+            Match(Char::Literal(c), _) => alphabet.push(*c),
+            Match(Char::Except(excluded), _) => alphabet.extend(excluded.iter()),
+            _ => {}
+        }
+    }
+    alphabet.sort();
+    alphabet.dedup();
+    alphabet
+}
+
+/**
+ * Whether a label matches a symbol, where None stands for every char
+ * outside the alphabet. Labels only ever name chars in the alphabet.
+ */
+fn symbol_matches(label: &Char, symbol: Option<char>) -> bool {
+    match symbol {
+        Some(c) => label.matches(c),
+        None => !matches!(label, Char::Literal(_)),
+    }
+}
+
+/**
+ * The sorted set of Match and End states reachable from the given
+ * states by epsilon transitions.
+ */
+fn closure(nfa: &NFA, states: &[StateId]) -> Vec<StateId> {
+    let mut stack: Vec<StateId> = states.to_vec();
+    let mut seen = vec![false; nfa.states.len()];
+    let mut subset = Vec::new();
+    while let Some(id) = stack.pop() {
+        if seen[id] {
+            continue;
+        }
+        seen[id] = true;
+        match nfa.states[id] {
+            Start(Some(next)) => stack.push(next),
+            Split(Some(lhs), Some(rhs)) => {
+                stack.push(lhs);
+                stack.push(rhs);
+            }
+            Match(_, Some(_)) | End => subset.push(id),
+            _ => {}
+        }
+    }
+    subset.sort();
+    subset
+}
+
+#[cfg(test)]
+mod dfa_tests {
+    use super::*;
+
+    #[test]
+    fn determinize() {
+        let nfa = NFA::from("(a|b)*abb").unwrap();
+        let dfa = nfa.to_dfa();
+        for input in &["abb", "aabb", "babb", "ab", "abba", ""] {
+            assert_eq!(dfa.accepts_exactly(input), nfa.accepts_exactly(input));
+            assert_eq!(dfa.accepts(input), nfa.accepts(input));
+        }
+    }
+
+    #[test]
+    fn determinize_any() {
+        let dfa = NFA::from("a.c").unwrap().to_dfa();
+        assert!(dfa.accepts_exactly("abc"));
+        assert!(dfa.accepts_exactly("aac"));
+        assert!(dfa.accepts_exactly("a~c"));
+        assert!(!dfa.accepts_exactly("ac"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let nfa = NFA::from("x(a|bc)*y").unwrap();
+        let back = nfa.to_dfa().to_nfa();
+        for input in &["xy", "xay", "xbcay", "xby", "x", "xaay!"] {
+            assert_eq!(back.accepts_exactly(input), nfa.accepts_exactly(input));
+        }
+    }
+
+    #[test]
+    fn intersection() {
+        let dfa = NFA::from("a*b*").unwrap().to_dfa() & NFA::from("(ab)*").unwrap().to_dfa();
+        assert!(dfa.accepts_exactly(""));
+        assert!(dfa.accepts_exactly("ab"));
+        assert!(!dfa.accepts_exactly("abab"));
+        assert!(!dfa.accepts_exactly("aab"));
+    }
+
+    #[test]
+    fn complement() {
+        let dfa = !NFA::from("a.*").unwrap().to_dfa();
+        assert!(dfa.accepts_exactly(""));
+        assert!(dfa.accepts_exactly("ba"));
+        assert!(!dfa.accepts_exactly("a"));
+        assert!(!dfa.accepts_exactly("abc"));
+    }
+
+    #[test]
+    fn difference() {
+        let dfa = &NFA::from(".*").unwrap().to_dfa() - &NFA::from(".*x.*").unwrap().to_dfa();
+        assert!(dfa.accepts_exactly("abc"));
+        assert!(!dfa.accepts_exactly("axc"));
+    }
+
+    #[test]
+    fn shortest() {
+        let dfa = NFA::from("b(a|b)+|aaaa|c").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), Some(String::from("c")));
+        let dfa = NFA::from("ba|ab|bb").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), Some(String::from("ab")));
+        let dfa = NFA::from("a*").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), Some(String::new()));
+    }
+
+    #[test]
+    fn shortest_other_char() {
+        let dfa = NFA::from("a.").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), Some(String::from("aa")));
+        let dfa = !NFA::from(".*").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), None);
+        let dfa = NFA::from("~(a|b)*").unwrap().to_dfa();
+        assert_eq!(dfa.shortest_accepted(), Some(String::from("c")));
+    }
+
+    #[test]
+    fn empty_language_to_nfa() {
+        let dfa = NFA::from("a").unwrap().to_dfa() & NFA::from("b").unwrap().to_dfa();
+        let nfa = dfa.to_nfa();
+        assert!(!nfa.accepts(""));
+        assert!(!nfa.accepts("a"));
+        assert!(!nfa.accepts("b"));
+    }
+}
@@ -1,46 +1,1997 @@
+use super::mark_live;
 use super::Char;
+use super::State;
 use super::State::*;
+use super::StateId;
 use super::NFA;
+use crate::parser::AST;
+use crate::regex::Regex;
+use crate::tokenizer::is_metachar;
+use std::collections::HashSet;
 
 /**
  * Helper functions for visualizing our NFA
  * Both at the internal representation level and in dot format
  * to generate a graphical representation.
  */
+/**
+ * Generate a string of the internal structure of the NFA.
+ */
+pub fn nfa_dump(nfa: &NFA) -> String {
+    let mut s = String::new();
+    for (id, state) in nfa.states.iter().enumerate() {
+        s.push_str(&format!("{:03} | {:?}\n", id, state));
+    }
+    s
+}
+
+/**
+ * Generate a DOT structured string.
+ */
+pub fn nfa_dot(nfa: &NFA) -> String {
+    let mut dot = String::from("digraph nfa {rankdir=LR; \n\tnode [shape = circle];\n");
+    //let mut dot = String::from("digraph nfa {rankdir=LR;\n");
+    for (id, state) in nfa.states.iter().enumerate() {
+        //     let concat = concat!("Start", id);
+        dot.push_str(&match state {
+            // Start(Some(next)) => format!("\tstart [shape=\"none\"]\n\tstart -> {}\n", next),
+            Start(Some(next)) => format!("\t{} [shape=\"diamond\", style=\"filled\", fillcolor=\"lightskyblue\", label=\"Start\"]\n\t{} -> {}\n", id, id, next),
+            Match(c, Some(next)) => format!("\t{} [style=\"filled\", fillcolor=\"palegreen2\"]{} -> {} [label=\"{}\"]\n", id, id, next, c),
+            Split(Some(lhs), Some(rhs)) => format!(
+                "\t{0} [style=\"filled\", fillcolor=\"plum\"]{0} -> {1} [label=\"ε\"]\n\t{0} -> {2} [label=\"ε\"]\n",
+                id, rhs, lhs
+            ),
+            End => format!("\t{} [shape=\"doublecircle\", style=\"filled\", fillcolor=\"lightsalmon\"]\n", id),
+            _ => String::new(),
+        });
+    }
+    dot += "}";
+    dot
+}
+
+/**
+ * Generate a DOT structured string in which the states and edges visited
+ * while simulating the NFA on `input` are highlighted and every state the
+ * input never reached is greyed out.
+ */
+pub fn nfa_dot_with_trace(nfa: &NFA, input: &str) -> String {
+    let trace = Trace::new(nfa, input);
+    let mut dot = format!(
+        "digraph nfa {{rankdir=LR; \n\tnode [shape = circle];\n\tlabel=\"{}\";\n",
+        escape_label(input)
+    );
+    for (id, state) in nfa.states.iter().enumerate() {
+        let style = if trace.visited[id] {
+            match state {
+                Start(_) => "fillcolor=\"lightskyblue\", color=\"red\", penwidth=2",
+                Match(_, _) => "fillcolor=\"palegreen2\", color=\"red\", penwidth=2",
+                Split(_, _) => "fillcolor=\"plum\", color=\"red\", penwidth=2",
+                End => "fillcolor=\"lightsalmon\", color=\"red\", penwidth=2",
+            }
+        } else {
+            "fillcolor=\"gray90\", color=\"gray60\", fontcolor=\"gray60\""
+        };
+        dot.push_str(&match state {
+            Start(_) => format!(
+                "\t{} [shape=\"diamond\", style=\"filled\", {}, label=\"Start\"]\n",
+                id, style
+            ),
+            End => format!(
+                "\t{} [shape=\"doublecircle\", style=\"filled\", {}]\n",
+                id, style
+            ),
+            _ => format!("\t{} [style=\"filled\", {}]\n", id, style),
+        });
+        for (to, label) in edges(state) {
+            let color = if trace.edges.contains(&(id, to)) {
+                "color=\"red\", penwidth=2"
+            } else {
+                "color=\"gray60\", fontcolor=\"gray60\""
+            };
+            match label {
+                Some(label) => dot.push_str(&format!(
+                    "\t{} -> {} [label=\"{}\", {}]\n",
+                    id,
+                    to,
+                    escape_label(&label),
+                    color
+                )),
+                None => dot.push_str(&format!("\t{} -> {} [{}]\n", id, to, color)),
+            }
+        }
+    }
+    dot += "}";
+    dot
+}
+
+/**
+ * The outgoing edges of a state, each paired with its label. The edge
+ * out of Start is unlabeled, which is why the label is optional.
+ */
+fn edges(state: &State) -> Vec<(StateId, Option<String>)> {
+    match state {
+        Start(Some(next)) => vec![(*next, None)],
+        Match(c, Some(next)) => vec![(*next, Some(c.to_string()))],
+        Split(Some(lhs), Some(rhs)) => vec![
+            (*rhs, Some(String::from("ε"))),
+            (*lhs, Some(String::from("ε"))),
+        ],
+        _ => vec![],
+    }
+}
+
+/**
+ * Escape a string so that it can be used inside a quoted DOT label.
+ */
+fn escape_label(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/**
+ * A record of every state and edge touched while simulating an NFA on
+ * a particular input string.
+ */
+struct Trace {
+    visited: Vec<bool>,
+    edges: HashSet<(StateId, StateId)>,
+}
+
+impl Trace {
+    /**
+     * Simulate the NFA on the input, stepping through every character
+     * until either the input runs out or no states remain alive.
+     */
+    fn new(nfa: &NFA, input: &str) -> Trace {
+        let mut trace = Trace {
+            visited: vec![false; nfa.states.len()],
+            edges: HashSet::new(),
+        };
+
+        let mut curr_states = Vec::new();
+        trace.follow(nfa, nfa.start, &mut curr_states);
+
+        for c in input.chars() {
+            if curr_states.is_empty() {
+                break;
+            }
+            let mut next_states = Vec::new();
+            for state in curr_states {
+                if let Match(m, Some(next)) = &nfa.states[state] {
+                    if m.matches(c) {
+                        trace.edges.insert((state, *next));
+                        trace.follow(nfa, *next, &mut next_states);
+                    }
+                }
+            }
+            curr_states = next_states;
+        }
+        trace
+    }
+
+    /**
+     * Mark a state as visited and follow its epsilon transitions,
+     * collecting the Match and End states it leads to.
+     */
+    fn follow(&mut self, nfa: &NFA, id: StateId, states: &mut Vec<StateId>) {
+        if states.contains(&id) {
+            return;
+        }
+        self.visited[id] = true;
+        match &nfa.states[id] {
+            Start(Some(next)) => {
+                self.edges.insert((id, *next));
+                self.follow(nfa, *next, states);
+            }
+            Split(Some(lhs), Some(rhs)) => {
+                // Splits are recorded so that epsilon cycles terminate
+                states.push(id);
+                self.edges.insert((id, *rhs));
+                self.follow(nfa, *rhs, states);
+                self.edges.insert((id, *lhs));
+                self.follow(nfa, *lhs, states);
+            }
+            Match(_, Some(_)) | End => states.push(id),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+
+    #[test]
+    fn trace_alt() {
+        let nfa = NFA::from("a|b").unwrap();
+        let dot = nfa_dot_with_trace(&nfa, "b");
+        assert!(dot.contains("\t2 [style=\"filled\", fillcolor=\"palegreen2\", color=\"red\""));
+        assert!(dot.contains("\t2 -> 4 [label=\"b\", color=\"red\""));
+        assert!(dot.contains("\t1 [style=\"filled\", fillcolor=\"palegreen2\", color=\"red\""));
+        assert!(dot.contains("\t1 -> 4 [label=\"a\", color=\"gray60\""));
+        assert!(dot
+            .contains("\t4 [shape=\"doublecircle\", style=\"filled\", fillcolor=\"lightsalmon\""));
+    }
+
+    #[test]
+    fn trace_closure() {
+        let nfa = NFA::from("(ab)*c").unwrap();
+        let dot = nfa_dot_with_trace(&nfa, "abab");
+        assert!(dot.contains("\t1 -> 2 [label=\"a\", color=\"red\""));
+        assert!(dot.contains("\t2 -> 3 [label=\"b\", color=\"red\""));
+        assert!(dot.contains("\t4 -> 5 [label=\"c\", color=\"gray60\""));
+        assert!(dot.contains("\t5 [shape=\"doublecircle\", style=\"filled\", fillcolor=\"gray90\""));
+    }
+
+    #[test]
+    fn trace_dead_input() {
+        let nfa = NFA::from("ab").unwrap();
+        let dot = nfa_dot_with_trace(&nfa, "xb");
+        assert!(dot.contains("\t1 [style=\"filled\", fillcolor=\"palegreen2\", color=\"red\""));
+        assert!(dot.contains("\t1 -> 2 [label=\"a\", color=\"gray60\""));
+        assert!(dot.contains("\t2 [style=\"filled\", fillcolor=\"gray90\""));
+    }
+
+    #[test]
+    fn trace_escapes_input() {
+        let nfa = NFA::from("a").unwrap();
+        let dot = nfa_dot_with_trace(&nfa, "\"a\"");
+        assert!(dot.contains("label=\"\\\"a\\\"\";"));
+    }
+}
+
+/**
+ * Generate a JSON structured string of the NFA's states. Each state is
+ * an object with an "id" and a "type" of "Start", "Match", "Split" or
+ * "End", along with the ids of the states its transitions lead to.
+ */
+pub fn nfa_json(nfa: &NFA) -> String {
+    let states: Vec<String> = nfa
+        .states
+        .iter()
+        .enumerate()
+        .map(|(id, state)| match state {
+            Start(next) => format!(
+                "{{\"id\":{},\"type\":\"Start\",\"next\":{}}}",
+                id,
+                json_id(*next)
+            ),
+            Match(c, next) => format!(
+                "{{\"id\":{},\"type\":\"Match\",\"match\":{},\"next\":{}}}",
+                id,
+                json_char(c),
+                json_id(*next)
+            ),
+            Split(lhs, rhs) => format!(
+                "{{\"id\":{},\"type\":\"Split\",\"lhs\":{},\"rhs\":{}}}",
+                id,
+                json_id(*lhs),
+                json_id(*rhs)
+            ),
+            End => format!("{{\"id\":{},\"type\":\"End\"}}", id),
+        })
+        .collect();
+    format!(
+        "{{\"start\":{},\"states\":[{}]}}",
+        nfa.start,
+        states.join(",")
+    )
+}
+
+/**
+ * Generate a Mermaid flowchart of the NFA.
+ */
+pub fn nfa_mermaid(nfa: &NFA) -> String {
+    let mut mermaid = String::from("flowchart LR\n");
+    for (id, state) in nfa.states.iter().enumerate() {
+        mermaid.push_str(&match state {
+            Start(_) => format!("    s{}{{\"Start\"}}\n", id),
+            End => format!("    s{}(((\"{}\")))\n", id, id),
+            _ => format!("    s{}((\"{}\"))\n", id, id),
+        });
+        for (to, label) in edges(state) {
+            mermaid.push_str(&match label {
+                Some(label) => format!(
+                    "    s{} -- \"{}\" --> s{}\n",
+                    id,
+                    escape_mermaid(&label),
+                    to
+                ),
+                None => format!("    s{} --> s{}\n", id, to),
+            });
+        }
+    }
+    mermaid
+}
+
+/**
+ * Generate a GraphML document of the NFA. Every node carries its state
+ * type and every edge carries its label.
+ */
+pub fn nfa_graphml(nfa: &NFA) -> String {
+    let mut graphml = graphml_header("nfa");
+    for (id, state) in nfa.states.iter().enumerate() {
+        let kind = match state {
+            Start(_) => "Start",
+            Match(_, _) => "Match",
+            Split(_, _) => "Split",
+            End => "End",
+        };
+        graphml.push_str(&format!(
+            "    <node id=\"s{}\"><data key=\"kind\">{}</data></node>\n",
+            id, kind
+        ));
+    }
+    for (id, state) in nfa.states.iter().enumerate() {
+        for (to, label) in edges(state) {
+            graphml.push_str(&format!("    <edge source=\"s{}\" target=\"s{}\">", id, to));
+            if let Some(label) = label {
+                graphml.push_str(&format!(
+                    "<data key=\"label\">{}</data>",
+                    escape_xml(&label)
+                ));
+            }
+            graphml.push_str("</edge>\n");
+        }
+    }
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+/**
+ * Generate a JSON structured string of a parsed AST. Every node is an
+ * object with a "type" naming its AST variant. Binary nodes hold "lhs"
+ * and "rhs", unary nodes hold "child" and Char nodes hold "value".
+ * Group nodes additionally hold their capture group "index".
+ */
+pub fn ast_json(ast: &AST) -> String {
+    match ast {
+        AST::Alternation(lhs, rhs) => format!(
+            "{{\"type\":\"Alternation\",\"lhs\":{},\"rhs\":{}}}",
+            ast_json(lhs),
+            ast_json(rhs)
+        ),
+        AST::Catenation(lhs, rhs) => format!(
+            "{{\"type\":\"Catenation\",\"lhs\":{},\"rhs\":{}}}",
+            ast_json(lhs),
+            ast_json(rhs)
+        ),
+        AST::Closure(child) => format!("{{\"type\":\"Closure\",\"child\":{}}}", ast_json(child)),
+        AST::OneOrMore(child) => {
+            format!("{{\"type\":\"OneOrMore\",\"child\":{}}}", ast_json(child))
+        }
+        AST::Char(c) => format!(
+            "{{\"type\":\"Char\",\"value\":{}}}",
+            json_string(&c.to_string())
+        ),
+        AST::AnyChar => String::from("{\"type\":\"AnyChar\"}"),
+        AST::Group(idx, child) => format!(
+            "{{\"type\":\"Group\",\"index\":{},\"child\":{}}}",
+            idx,
+            ast_json(child)
+        ),
+        AST::Intersection(lhs, rhs) => format!(
+            "{{\"type\":\"Intersection\",\"lhs\":{},\"rhs\":{}}}",
+            ast_json(lhs),
+            ast_json(rhs)
+        ),
+        AST::Complement(child) => {
+            format!("{{\"type\":\"Complement\",\"child\":{}}}", ast_json(child))
+        }
+    }
+}
+
+/**
+ * Generate a DOT structured string of a parsed AST as a tree.
+ */
+pub fn ast_dot(ast: &AST) -> String {
+    let (labels, links) = ast_tree(ast);
+    let mut dot = String::from("digraph ast {\n\tnode [shape = box];\n");
+    for (id, label) in labels.iter().enumerate() {
+        dot.push_str(&format!("\t{} [label=\"{}\"]\n", id, escape_label(label)));
+    }
+    for (from, to) in links {
+        dot.push_str(&format!("\t{} -> {}\n", from, to));
+    }
+    dot += "}";
+    dot
+}
+
+/**
+ * Generate a Mermaid flowchart of a parsed AST as a tree.
+ */
+pub fn ast_mermaid(ast: &AST) -> String {
+    let (labels, links) = ast_tree(ast);
+    let mut mermaid = String::from("flowchart TD\n");
+    for (id, label) in labels.iter().enumerate() {
+        mermaid.push_str(&format!("    n{}[\"{}\"]\n", id, escape_mermaid(label)));
+    }
+    for (from, to) in links {
+        mermaid.push_str(&format!("    n{} --> n{}\n", from, to));
+    }
+    mermaid
+}
+
+/**
+ * Generate a GraphML document of a parsed AST as a tree.
+ */
+pub fn ast_graphml(ast: &AST) -> String {
+    let (labels, links) = ast_tree(ast);
+    let mut graphml = graphml_header("ast");
+    for (id, label) in labels.iter().enumerate() {
+        graphml.push_str(&format!(
+            "    <node id=\"n{}\"><data key=\"kind\">{}</data></node>\n",
+            id,
+            escape_xml(label)
+        ));
+    }
+    for (from, to) in links {
+        graphml.push_str(&format!(
+            "    <edge source=\"n{}\" target=\"n{}\"></edge>\n",
+            from, to
+        ));
+    }
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+/**
+ * Flatten an AST into preorder node labels and parent to child links.
+ */
+fn ast_tree(ast: &AST) -> (Vec<String>, Vec<(usize, usize)>) {
+    fn walk(ast: &AST, labels: &mut Vec<String>, links: &mut Vec<(usize, usize)>) -> usize {
+        let id = labels.len();
+        let (label, children): (String, Vec<&AST>) = match ast {
+            AST::Alternation(lhs, rhs) => (String::from("Alternation"), vec![lhs, rhs]),
+            AST::Catenation(lhs, rhs) => (String::from("Catenation"), vec![lhs, rhs]),
+            AST::Closure(child) => (String::from("Closure"), vec![child]),
+            AST::OneOrMore(child) => (String::from("OneOrMore"), vec![child]),
+            AST::Char(c) => (format!("Char {}", c), vec![]),
+            AST::AnyChar => (String::from("AnyChar"), vec![]),
+            AST::Group(idx, child) => (format!("Group {}", idx), vec![child]),
+            AST::Intersection(lhs, rhs) => (String::from("Intersection"), vec![lhs, rhs]),
+            AST::Complement(child) => (String::from("Complement"), vec![child]),
+        };
+        labels.push(label);
+        for child in children {
+            let child_id = walk(child, labels, links);
+            links.push((id, child_id));
+        }
+        id
+    }
+
+    let mut labels = Vec::new();
+    let mut links = Vec::new();
+    walk(ast, &mut labels, &mut links);
+    (labels, links)
+}
+
+/**
+ * The opening of a GraphML document declaring the "kind" node attribute
+ * and the "label" edge attribute.
+ */
+fn graphml_header(id: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n\
+         \x20 <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>\n\
+         \x20 <key id=\"label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n\
+         \x20 <graph id=\"{}\" edgedefault=\"directed\">\n",
+        id
+    )
+}
+
+/**
+ * JSON representation of a Match state's label.
+ */
+fn json_char(c: &Char) -> String {
+    match c {
+        Char::Literal(l) => format!(
+            "{{\"kind\":\"literal\",\"char\":{}}}",
+            json_string(&l.to_string())
+        ),
+        Char::Any => String::from("{\"kind\":\"any\"}"),
+        Char::Except(excluded) => {
+            let chars: Vec<String> = excluded
+                .iter()
+                .map(|c| json_string(&c.to_string()))
+                .collect();
+            format!("{{\"kind\":\"except\",\"chars\":[{}]}}", chars.join(","))
+        }
+    }
+}
+
+/**
+ * JSON representation of a possibly unjoined transition.
+ */
+fn json_id(id: Option<StateId>) -> String {
+    match id {
+        Some(id) => id.to_string(),
+        None => String::from("null"),
+    }
+}
+
+/**
+ * Quote and escape a string as a JSON string literal.
+ */
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/**
+ * Escape a string for use inside a quoted Mermaid label.
+ */
+fn escape_mermaid(label: &str) -> String {
+    label.replace('"', "#quot;")
+}
+
+/**
+ * Escape a string for use as XML character data.
+ */
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod export_tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn json_nfa() {
+        let nfa = NFA::from("a|.").unwrap();
+        assert_eq!(
+            nfa_json(&nfa),
+            "{\"start\":0,\"states\":[\
+             {\"id\":0,\"type\":\"Start\",\"next\":3},\
+             {\"id\":1,\"type\":\"Match\",\"match\":{\"kind\":\"literal\",\"char\":\"a\"},\"next\":4},\
+             {\"id\":2,\"type\":\"Match\",\"match\":{\"kind\":\"any\"},\"next\":4},\
+             {\"id\":3,\"type\":\"Split\",\"lhs\":1,\"rhs\":2},\
+             {\"id\":4,\"type\":\"End\"}]}"
+        );
+    }
+
+    #[test]
+    fn json_ast() {
+        let ast = Parser::parse(Tokenizer::new("(a|\")*.+")).unwrap();
+        assert_eq!(
+            ast_json(&ast),
+            "{\"type\":\"Catenation\",\
+             \"lhs\":{\"type\":\"Closure\",\"child\":{\"type\":\"Alternation\",\
+             \"lhs\":{\"type\":\"Char\",\"value\":\"a\"},\
+             \"rhs\":{\"type\":\"Char\",\"value\":\"\\\"\"}}},\
+             \"rhs\":{\"type\":\"OneOrMore\",\"child\":{\"type\":\"AnyChar\"}}}"
+        );
+    }
+
+    #[test]
+    fn mermaid_nfa() {
+        let nfa = NFA::from("a*").unwrap();
+        assert_eq!(
+            nfa_mermaid(&nfa),
+            "flowchart LR\n    s0{\"Start\"}\n    s0 --> s2\n    s1((\"1\"))\n    s1 -- \"a\" --> s2\n    s2((\"2\"))\n    s2 -- \"ε\" --> s3\n    s2 -- \"ε\" --> s1\n    s3(((\"3\")))\n"
+        );
+    }
+
+    #[test]
+    fn mermaid_ast() {
+        let ast = Parser::parse(Tokenizer::new("ab")).unwrap();
+        assert_eq!(
+            ast_mermaid(&ast),
+            "flowchart TD\n    n0[\"Catenation\"]\n    n1[\"Char a\"]\n    n2[\"Char b\"]\n    n0 --> n1\n    n0 --> n2\n"
+        );
+    }
+
+    #[test]
+    fn graphml_nfa() {
+        let nfa = NFA::from("<").unwrap();
+        let graphml = nfa_graphml(&nfa);
+        assert!(graphml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml"));
+        assert!(graphml.contains("    <node id=\"s1\"><data key=\"kind\">Match</data></node>\n"));
+        assert!(graphml.contains(
+            "    <edge source=\"s1\" target=\"s2\"><data key=\"label\">&lt;</data></edge>\n"
+        ));
+        assert!(graphml.ends_with("  </graph>\n</graphml>\n"));
+    }
+
+    #[test]
+    fn graphml_ast() {
+        let ast = Parser::parse(Tokenizer::new("a*")).unwrap();
+        let graphml = ast_graphml(&ast);
+        assert!(graphml.contains("    <node id=\"n0\"><data key=\"kind\">Closure</data></node>\n"));
+        assert!(graphml.contains("    <edge source=\"n0\" target=\"n1\"></edge>\n"));
+    }
+
+    #[test]
+    fn dot_ast() {
+        let ast = Parser::parse(Tokenizer::new("a|b")).unwrap();
+        assert_eq!(
+            ast_dot(&ast),
+            "digraph ast {\n\tnode [shape = box];\n\t0 [label=\"Alternation\"]\n\t1 [label=\"Char a\"]\n\t2 [label=\"Char b\"]\n\t0 -> 1\n\t0 -> 2\n}"
+        );
+    }
+}
+
+/*
+ * Helper functions for analyzing the language an NFA accepts, used to
+ * lint patterns before they are put to work.
+ */
+
+/**
+ * A summary of the language an NFA accepts.
+ */
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    pub empty: bool,
+    pub finite: bool,
+    pub shortest: Option<String>,
+    pub longest: Option<String>,
+}
+
+/**
+ * Analyze the language an NFA accepts all at once.
+ */
+pub fn analyze(nfa: &NFA) -> Analysis {
+    Analysis {
+        empty: accepts_nothing(nfa),
+        finite: is_finite(nfa),
+        shortest: shortest_accepted(nfa),
+        longest: longest_accepted(nfa),
+    }
+}
+
+/**
+ * Whether the NFA accepts no strings at all, which is the case exactly
+ * when its end state cannot be reached from its start state.
+ */
+pub fn accepts_nothing(nfa: &NFA) -> bool {
+    !live_states(nfa)[nfa.start]
+}
+
+/**
+ * Whether the NFA accepts only finitely many strings. Once the NFA is
+ * trimmed down to the states that are both reachable and live, the
+ * language is infinite exactly when some Match edge lies on a cycle,
+ * which is when both its ends share a strongly connected component.
+ * Cycles made only of epsilon edges add no new strings.
+ */
+pub fn is_finite(nfa: &NFA) -> bool {
+    let trimmed = trimmed_states(nfa);
+    let components = components(nfa, &trimmed);
+    !nfa.states
+        .iter()
+        .enumerate()
+        .any(|(id, state)| match state {
+            Match(_, Some(next)) => {
+                trimmed[id] && trimmed[*next] && components[id] == components[*next]
+            }
+            _ => false,
+        })
+}
+
+/**
+ * The shortest string the NFA accepts, or None if it accepts nothing.
+ * Ties are broken by choosing the alphabetically first.
+ */
+pub fn shortest_accepted(nfa: &NFA) -> Option<String> {
+    nfa.to_dfa().shortest_accepted()
+}
+
+/**
+ * The longest string the NFA accepts, or None if it accepts nothing or
+ * accepts strings of unbounded length. The longest string from each
+ * state to the end state is relaxed until nothing changes, which must
+ * happen because a finite language leaves no Match edge on a cycle.
+ */
+pub fn longest_accepted(nfa: &NFA) -> Option<String> {
+    if accepts_nothing(nfa) || !is_finite(nfa) {
+        return None;
+    }
+
+    let trimmed = trimmed_states(nfa);
+    let mut longest: Vec<Option<Vec<char>>> = vec![None; nfa.states.len()];
+    longest[nfa.states.len() - 1] = Some(vec![]);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (id, state) in nfa.states.iter().enumerate() {
+            if !trimmed[id] {
+                continue;
+            }
+            let candidates: Vec<Vec<char>> = match state {
+                Start(Some(next)) => longest[*next].iter().cloned().collect(),
+                Split(Some(lhs), Some(rhs)) => longest[*lhs]
+                    .iter()
+                    .chain(longest[*rhs].iter())
+                    .cloned()
+                    .collect(),
+                Match(c, Some(next)) => longest[*next]
+                    .iter()
+                    .map(|rest| {
+                        let mut chars = vec![c.example()];
+                        chars.extend(rest);
+                        chars
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            for candidate in candidates {
+                let better = match &longest[id] {
+                    Some(current) => candidate.len() > current.len(),
+                    None => true,
+                };
+                if better {
+                    longest[id] = Some(candidate);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    longest[nfa.start]
+        .as_ref()
+        .map(|chars| chars.iter().collect())
+}
+
+/**
+ * Find which states are reachable from the start state and can also
+ * reach the end state.
+ */
+fn trimmed_states(nfa: &NFA) -> Vec<bool> {
+    let live = live_states(nfa);
+    let mut trimmed = vec![false; nfa.states.len()];
+    let mut stack = vec![nfa.start];
+    while let Some(id) = stack.pop() {
+        if trimmed[id] || !live[id] {
+            continue;
+        }
+        trimmed[id] = true;
+        for (next, _) in edges(&nfa.states[id]) {
+            stack.push(next);
+        }
+    }
+    trimmed
+}
+
+/**
+ * Label each of the given states with the strongly connected component
+ * it belongs to, using Tarjan's algorithm on the edges between them.
+ */
+fn components(nfa: &NFA, included: &[bool]) -> Vec<usize> {
+    struct Tarjan<'a> {
+        nfa: &'a NFA,
+        included: &'a [bool],
+        index: Vec<Option<usize>>,
+        lowlink: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<StateId>,
+        component: Vec<usize>,
+        next_index: usize,
+        next_component: usize,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, id: StateId) {
+            self.index[id] = Some(self.next_index);
+            self.lowlink[id] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(id);
+            self.on_stack[id] = true;
+
+            for (next, _) in edges(&self.nfa.states[id]) {
+                if !self.included[next] {
+                    continue;
+                }
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.lowlink[id] = self.lowlink[id].min(self.lowlink[next]);
+                    }
+                    Some(index) if self.on_stack[next] => {
+                        self.lowlink[id] = self.lowlink[id].min(index);
+                    }
+                    _ => {}
+                }
+            }
+
+            if Some(self.lowlink[id]) == self.index[id] {
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    self.component[member] = self.next_component;
+                    if member == id {
+                        break;
+                    }
+                }
+                self.next_component += 1;
+            }
+        }
+    }
+
+    let len = nfa.states.len();
+    let mut tarjan = Tarjan {
+        nfa,
+        included,
+        index: vec![None; len],
+        lowlink: vec![0; len],
+        on_stack: vec![false; len],
+        stack: Vec::new(),
+        component: vec![0; len],
+        next_index: 0,
+        next_component: 0,
+    };
+    for (id, &included) in included.iter().enumerate() {
+        if included && tarjan.index[id].is_none() {
+            tarjan.visit(id);
+        }
+    }
+    tarjan.component
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert!(accepts_nothing(&NFA::from("a&b").unwrap()));
+        assert!(accepts_nothing(&NFA::from("~(.*)").unwrap()));
+        assert!(!accepts_nothing(&NFA::from("a*").unwrap()));
+        assert!(!accepts_nothing(&NFA::from("a(b&b)").unwrap()));
+    }
+
+    #[test]
+    fn finite() {
+        assert!(is_finite(&NFA::from("abc|de").unwrap()));
+        assert!(is_finite(&NFA::from("a(b|c)(d|e)").unwrap()));
+        assert!(is_finite(&NFA::from("a&b").unwrap()));
+        assert!(!is_finite(&NFA::from("ab*").unwrap()));
+        assert!(!is_finite(&NFA::from("(a|b)+").unwrap()));
+    }
+
+    #[test]
+    fn finite_epsilon_cycle() {
+        // (~(.+))* only ever accepts the empty string
+        assert!(is_finite(&NFA::from("(~(.+))*").unwrap()));
+        // the closure over b is dead, so it cannot repeat
+        assert!(is_finite(&NFA::from("a|(b&c)*").unwrap()));
+    }
+
+    #[test]
+    fn shortest() {
+        assert_eq!(
+            shortest_accepted(&NFA::from("abc|de").unwrap()),
+            Some(String::from("de"))
+        );
+        assert_eq!(
+            shortest_accepted(&NFA::from("x.+y").unwrap()),
+            Some(String::from("xay"))
+        );
+        assert_eq!(
+            shortest_accepted(&NFA::from("a*").unwrap()),
+            Some(String::new())
+        );
+        assert_eq!(shortest_accepted(&NFA::from("a&b").unwrap()), None);
+    }
+
+    #[test]
+    fn longest() {
+        assert_eq!(
+            longest_accepted(&NFA::from("abc|de").unwrap()),
+            Some(String::from("abc"))
+        );
+        assert_eq!(
+            longest_accepted(&NFA::from("a(b|cd)(.|ef)").unwrap()),
+            Some(String::from("acdef"))
+        );
+        assert_eq!(longest_accepted(&NFA::from("ab*").unwrap()), None);
+        assert_eq!(longest_accepted(&NFA::from("a&b").unwrap()), None);
+    }
+
+    #[test]
+    fn analysis() {
+        // every string of c's and ab's that is at most three chars long
+        let analysis = analyze(&NFA::from("(ab|c)+&~(.....*)").unwrap());
+        assert!(!analysis.empty);
+        assert!(analysis.finite);
+        assert_eq!(analysis.shortest, Some(String::from("c")));
+        assert_eq!(analysis.longest.map(|s| s.len()), Some(3));
+    }
+}
+
+/*
+ * Helper functions for exhaustively listing the strings an NFA accepts.
+ */
+
+/**
+ * The chars an NFA's strings are enumerated over when none are given:
+ * every char its pattern names, plus one char standing in for all the
+ * others, which `.` and complements can match.
+ */
+pub fn default_alphabet(nfa: &NFA) -> Vec<char> {
+    nfa.to_dfa().sample_alphabet()
+}
+
+/**
+ * A lazy iterator over every distinct string made of chars from the
+ * alphabet, no longer than max_len, that the NFA accepts in full. The
+ * strings come in shortlex order: shorter strings first, and strings of
+ * the same length in alphabetical order.
+ */
+pub fn enumerate(nfa: &NFA, alphabet: &[char], max_len: usize) -> Enumerate {
+    let dfa = nfa.to_dfa();
+    let mut alphabet = alphabet.to_vec();
+    alphabet.sort();
+    alphabet.dedup();
+
+    // Only the chars of the alphabet can lead a state to acceptance
+    let accepting: Vec<bool> = (0..dfa.len()).map(|q| dfa.is_accepting(q)).collect();
+    let step = |q: usize, c: &char| dfa.step(q, *c);
+    let live = mark_live(accepting.clone(), |q| {
+        alphabet.iter().map(move |c| step(q, c))
+    });
+    let mut frontier = vec![false; dfa.len()];
+    frontier[0] = live[0];
+    let ends = vec![accepting];
+
+    Enumerate {
+        dfa,
+        live,
+        alphabet,
+        max_len,
+        len: 0,
+        frontier,
+        ends,
+        stack: Vec::new(),
+        prefix: String::new(),
+    }
+}
+
+/**
+ * The iterator returned by `enumerate`. The strings of each length are
+ * found by a depth-first search of the DFA, so a string is visited once
+ * and only the current path is held in memory. A prefix is only extended
+ * along a char whose target state can still reach an accepting state in
+ * exactly the chars that remain, so the search never goes down a path
+ * that yields nothing. A state is live when chars of the alphabet can
+ * take it to an accepting state, and the enumeration ends once no live
+ * state can be reached in the current number of chars.
+ */
+pub struct Enumerate {
+    dfa: super::dfa::DFA,
+    live: Vec<bool>,
+    alphabet: Vec<char>,
+    max_len: usize,
+    // The length of the strings being enumerated
+    len: usize,
+    // The live states reachable from the start in exactly len chars
+    frontier: Vec<bool>,
+    // ends[r][q]: an accepting state can be reached from q in exactly r chars
+    ends: Vec<Vec<bool>>,
+    // The states along the current prefix, each with the next char to try
+    stack: Vec<(usize, usize)>,
+    prefix: String,
+}
+
+impl Enumerate {
+    /**
+     * Move on to the strings one char longer.
+     */
+    fn next_len(&mut self) {
+        let mut frontier = vec![false; self.dfa.len()];
+        for q in (0..self.dfa.len()).filter(|q| self.frontier[*q]) {
+            for c in self.alphabet.iter() {
+                let target = self.dfa.step(q, *c);
+                frontier[target] = self.live[target];
+            }
+        }
+        self.frontier = frontier;
+        self.len += 1;
+    }
+
+    /**
+     * Drop the last state of the current path, moving on to the next
+     * length once the whole search for this one is done.
+     */
+    fn backtrack(&mut self) {
+        self.stack.pop();
+        if self.stack.is_empty() {
+            self.next_len();
+        } else {
+            self.prefix.pop();
+        }
+    }
+
+    /**
+     * Extend `ends` until it covers strings of the current length.
+     */
+    fn extend_ends(&mut self) {
+        while self.ends.len() <= self.len {
+            let last = &self.ends[self.ends.len() - 1];
+            let next = (0..self.dfa.len())
+                .map(|q| self.alphabet.iter().any(|c| last[self.dfa.step(q, *c)]))
+                .collect();
+            self.ends.push(next);
+        }
+    }
+}
+
+impl Iterator for Enumerate {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if self.stack.is_empty() {
+                if self.len > self.max_len || !self.frontier.contains(&true) {
+                    return None;
+                }
+                self.extend_ends();
+                if self.ends[self.len][0] {
+                    self.stack.push((0, 0));
+                } else {
+                    self.next_len();
+                }
+                continue;
+            }
+
+            let depth = self.stack.len() - 1;
+            if depth == self.len {
+                let found = self.prefix.clone();
+                self.backtrack();
+                return Some(found);
+            }
+
+            let remaining = &self.ends[self.len - depth - 1];
+            let (q, from) = self.stack[depth];
+            let next = self.alphabet[from..]
+                .iter()
+                .enumerate()
+                .map(|(idx, c)| (from + idx, *c, self.dfa.step(q, *c)))
+                .find(|(_, _, target)| remaining[*target]);
+            match next {
+                Some((idx, c, target)) => {
+                    self.stack[depth].1 = idx + 1;
+                    self.stack.push((target, 0));
+                    self.prefix.push(c);
+                }
+                None => self.backtrack(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod enumerate_tests {
+    use super::*;
+
+    fn list(pattern: &str, alphabet: &str, max_len: usize) -> Vec<String> {
+        let alphabet: Vec<char> = alphabet.chars().collect();
+        enumerate(&NFA::from(pattern).unwrap(), &alphabet, max_len).collect()
+    }
+
+    #[test]
+    fn shortlex() {
+        assert_eq!(
+            list("(a|b)*", "ba", 2),
+            vec!["", "a", "b", "aa", "ab", "ba", "bb"]
+        );
+    }
+
+    #[test]
+    fn no_duplicates() {
+        assert_eq!(list("a|a|aa*", "a", 3), vec!["a", "aa", "aaa"]);
+        assert_eq!(list("(a*)*", "a", 2), vec!["", "a", "aa"]);
+    }
+
+    #[test]
+    fn finite_language_ends_early() {
+        assert_eq!(list("ab|c", "abc", usize::MAX), vec!["c", "ab"]);
+    }
+
+    #[test]
+    fn empty_language() {
+        assert!(list("a&b", "ab", 5).is_empty());
+    }
+
+    #[test]
+    fn restricted_alphabet() {
+        assert_eq!(list(".b", "xb", 2), vec!["bb", "xb"]);
+        assert!(list("abc", "ab", 3).is_empty());
+    }
+
+    #[test]
+    fn lazy() {
+        let nfa = NFA::from(".*").unwrap();
+        let first: Vec<String> = enumerate(&nfa, &['0', '1'], usize::MAX)
+            .skip(3)
+            .take(2)
+            .collect();
+        assert_eq!(first, vec!["00", "01"]);
+    }
+
+    #[test]
+    fn lazy_within_a_length() {
+        // 2^40 strings of length 40, the first of which comes straight away
+        let nfa = NFA::from(&"(a|b)".repeat(40)).unwrap();
+        let first = enumerate(&nfa, &['a', 'b'], 40).next();
+        assert_eq!(first, Some("a".repeat(40)));
+    }
+
+    #[test]
+    fn nothing_over_the_alphabet() {
+        // .*c needs a c, which the alphabet leaves out
+        let nfa = NFA::from(".*c").unwrap();
+        assert_eq!(enumerate(&nfa, &['a', 'b'], usize::MAX).next(), None);
+    }
+
+    #[test]
+    fn default_alphabets() {
+        assert_eq!(
+            default_alphabet(&NFA::from("ba").unwrap()),
+            vec!['a', 'b', 'c']
+        );
+        assert_eq!(default_alphabet(&NFA::from("a.").unwrap()), vec!['a', 'b']);
+    }
+}
+
+/*
+ * Helper functions for counting the strings an NFA accepts.
+ */
+
+/**
+ * The longest strings that are counted. Counts are kept for every length
+ * up to the longest, so this bounds the memory counting takes.
+ */
+pub const MAX_COUNTED_LEN: usize = 4096;
+
+/**
+ * The number of strings of each length from 0 to max_len, made of chars
+ * from the alphabet, that the NFA accepts in full. A count is None when
+ * it is too large to fit in a u128. Returns an error when max_len is
+ * longer than `MAX_COUNTED_LEN`.
+ */
+pub fn count_lengths(
+    nfa: &NFA,
+    alphabet: &[char],
+    max_len: usize,
+) -> Result<Vec<Option<u128>>, String> {
+    let dfa = nfa.to_dfa();
+    let mut alphabet = alphabet.to_vec();
+    alphabet.sort();
+    alphabet.dedup();
+    Ok(suffix_counts(&dfa, &alphabet, max_len)?
+        .iter()
+        .map(|counts| counts[0])
+        .collect())
+}
+
+/**
+ * For each length n from 0 to max_len, the number of strings of length n
+ * that lead from each DFA state to acceptance. A string of length n
+ * from q is a char c followed by a string of length n - 1 from wherever
+ * q goes on c, so each length follows from the one before it. Counts
+ * that overflow stay None from then on.
+ */
+fn suffix_counts(
+    dfa: &super::dfa::DFA,
+    alphabet: &[char],
+    max_len: usize,
+) -> Result<Vec<Vec<Option<u128>>>, String> {
+    if max_len > MAX_COUNTED_LEN {
+        return Err(format!(
+            "Cannot count strings longer than {} chars",
+            MAX_COUNTED_LEN
+        ));
+    }
+    let states = dfa.len();
+    let mut counts: Vec<Vec<Option<u128>>> = Vec::with_capacity(max_len + 1);
+    counts.push(
+        (0..states)
+            .map(|q| Some(if dfa.is_accepting(q) { 1 } else { 0 }))
+            .collect(),
+    );
+    for n in 1..=max_len {
+        let shorter = &counts[n - 1];
+        let row = (0..states)
+            .map(|q| {
+                alphabet.iter().try_fold(0u128, |total, c| {
+                    shorter[dfa.step(q, *c)].and_then(|count| total.checked_add(count))
+                })
+            })
+            .collect();
+        counts.push(row);
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod count_tests {
+    use super::*;
+
+    fn counts(pattern: &str, alphabet: &str, max_len: usize) -> Vec<Option<u128>> {
+        let alphabet: Vec<char> = alphabet.chars().collect();
+        count_lengths(&NFA::from(pattern).unwrap(), &alphabet, max_len).unwrap()
+    }
+
+    #[test]
+    fn all_strings() {
+        assert_eq!(
+            counts(".*", "01", 4),
+            vec![Some(1), Some(2), Some(4), Some(8), Some(16)]
+        );
+    }
+
+    #[test]
+    fn overlapping_alternatives() {
+        // a|a|ab* accepts a, ab, abb, ... once each
+        assert_eq!(
+            counts("a|a|ab*", "ab", 3),
+            vec![Some(0), Some(1), Some(1), Some(1)]
+        );
+    }
+
+    #[test]
+    fn matches_enumeration() {
+        let nfa = NFA::from("(a|bc)*&~(.*cc.*)").unwrap();
+        let alphabet = ['a', 'b', 'c'];
+        let counted = count_lengths(&nfa, &alphabet, 6).unwrap();
+        for (len, count) in counted.iter().enumerate() {
+            let listed = enumerate(&nfa, &alphabet, 6)
+                .filter(|s| s.chars().count() == len)
+                .count();
+            assert_eq!(*count, Some(listed as u128));
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        let counted = counts(".*", "0123456789abcdef", 33);
+        assert_eq!(counted[31], Some(1 << 124));
+        assert_eq!(counted[32], None);
+        assert_eq!(counted[33], None);
+    }
+
+    #[test]
+    fn too_long() {
+        let nfa = NFA::from("a*").unwrap();
+        assert!(count_lengths(&nfa, &['a'], MAX_COUNTED_LEN).is_ok());
+        assert!(count_lengths(&nfa, &['a'], MAX_COUNTED_LEN + 1).is_err());
+        assert!(count_lengths(&nfa, &['a'], usize::MAX).is_err());
+    }
+}
+
+/*
+ * Helper functions for generating test strings that cover an NFA.
+ */
+
+/**
+ * A set of accepted strings that together pass through every coverable
+ * transition of an NFA, and the transitions no accepted string can pass
+ * through. A transition is a Match edge or one branch of a Split, given
+ * as the ids of the states it goes from and to.
+ */
+#[derive(Debug, PartialEq)]
+pub struct EdgeCoverage {
+    pub strings: Vec<String>,
+    pub unreachable: Vec<(StateId, StateId)>,
+}
+
+/**
+ * Generate a small set of accepted strings that cover every transition
+ * of the NFA that some accepted string can take. Each uncovered
+ * transition in turn is routed through by the path from the start state
+ * and on to the end state that passes through the fewest transitions
+ * already covered, and strings that turn out to cover nothing the
+ * others do not are dropped at the end.
+ */
+pub fn edge_coverage(nfa: &NFA) -> EdgeCoverage {
+    let trimmed = trimmed_states(nfa);
+    let end = nfa.states.len() - 1;
+
+    let (coverable, unreachable): (Vec<_>, Vec<_>) = nfa
+        .states
+        .iter()
+        .enumerate()
+        .flat_map(|(id, state)| match state {
+            Match(_, Some(next)) => vec![(id, *next)],
+            Split(Some(lhs), Some(rhs)) => vec![(id, *lhs), (id, *rhs)],
+            _ => vec![],
+        })
+        .partition(|(from, to)| trimmed[*from] && trimmed[*to]);
+
+    let mut covered: HashSet<(StateId, StateId)> = HashSet::new();
+    let mut paths: Vec<Vec<StateId>> = Vec::new();
+    for transition in coverable.iter() {
+        if covered.contains(transition) {
+            continue;
+        }
+        let (from, to) = *transition;
+        let mut path = cheapest_path(nfa, nfa.start, from, &trimmed, &covered);
+        path.extend(cheapest_path(nfa, to, end, &trimmed, &covered));
+        covered.extend(path.windows(2).map(|pair| (pair[0], pair[1])));
+        paths.push(path);
+    }
+
+    // Drop the paths, latest first, whose transitions the rest all cover
+    let taken: Vec<HashSet<(StateId, StateId)>> = paths
+        .iter()
+        .map(|path| path.windows(2).map(|pair| (pair[0], pair[1])).collect())
+        .collect();
+    let mut keep = vec![true; paths.len()];
+    for idx in (0..paths.len()).rev() {
+        let redundant = taken[idx].iter().all(|transition| {
+            (0..paths.len())
+                .any(|other| other != idx && keep[other] && taken[other].contains(transition))
+        });
+        if redundant {
+            keep[idx] = false;
+        }
+    }
+
+    let strings = paths
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(path, _)| {
+            path.iter()
+                .filter_map(|id| match &nfa.states[*id] {
+                    Match(c, _) => Some(c.example()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect();
+
+    EdgeCoverage {
+        strings,
+        unreachable,
+    }
+}
+
+/**
+ * The states along the path between two states that takes the fewest
+ * transitions already covered, staying within the given states. Both
+ * states must be among them, and the end must be reachable.
+ */
+fn cheapest_path(
+    nfa: &NFA,
+    from: StateId,
+    to: StateId,
+    included: &[bool],
+    covered: &HashSet<(StateId, StateId)>,
+) -> Vec<StateId> {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    let mut cost: Vec<Option<usize>> = vec![None; nfa.states.len()];
+    let mut parent: Vec<Option<StateId>> = vec![None; nfa.states.len()];
+    let mut heap = BinaryHeap::new();
+    cost[from] = Some(0);
+    heap.push(Reverse((0, from)));
+
+    while let Some(Reverse((c, id))) = heap.pop() {
+        if id == to {
+            break;
+        }
+        if cost[id].is_some_and(|best| c > best) {
+            continue;
+        }
+        for (next, _) in edges(&nfa.states[id]) {
+            if !included[next] {
+                continue;
+            }
+            let step = if covered.contains(&(id, next)) { 1 } else { 0 };
+            if cost[next].is_none_or(|best| c + step < best) {
+                cost[next] = Some(c + step);
+                parent[next] = Some(id);
+                heap.push(Reverse((c + step, next)));
+            }
+        }
+    }
+
+    let mut path = vec![to];
+    let mut id = to;
+    while id != from {
+        match parent[id] {
+            Some(prev) => id = prev,
+            None => break,
+        }
+        path.push(id);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod coverage_tests {
+    use super::*;
+
+    fn all_accepted(nfa: &NFA, coverage: &EdgeCoverage) {
+        for st in &coverage.strings {
+            assert!(nfa.accepts_exactly(st), "{:?} is not accepted", st);
+        }
+    }
+
+    #[test]
+    fn alternatives() {
+        let nfa = NFA::from("ab|cd|ef").unwrap();
+        let mut coverage = edge_coverage(&nfa);
+        all_accepted(&nfa, &coverage);
+        coverage.strings.sort();
+        assert_eq!(coverage.strings, vec!["ab", "cd", "ef"]);
+        assert!(coverage.unreachable.is_empty());
+    }
+
+    #[test]
+    fn nested_alternatives() {
+        let nfa = NFA::from("a(b|c)(d|e)").unwrap();
+        let coverage = edge_coverage(&nfa);
+        all_accepted(&nfa, &coverage);
+        assert_eq!(coverage.strings.len(), 2);
+    }
+
+    #[test]
+    fn closures() {
+        // both branches of the closure's split must be taken, so the
+        // loop is gone around at least once
+        let nfa = NFA::from("xa*").unwrap();
+        let coverage = edge_coverage(&nfa);
+        all_accepted(&nfa, &coverage);
+        assert_eq!(coverage.strings, vec!["xa"]);
+    }
+
+    #[test]
+    fn unreachable_transitions() {
+        let nfa = NFA::from("a|(b&c)").unwrap();
+        let coverage = edge_coverage(&nfa);
+        all_accepted(&nfa, &coverage);
+        assert_eq!(coverage.strings, vec!["a"]);
+        assert!(!coverage.unreachable.is_empty());
+    }
+
+    #[test]
+    fn empty_language() {
+        let coverage = edge_coverage(&NFA::from("a&b").unwrap());
+        assert!(coverage.strings.is_empty());
+        assert!(!coverage.unreachable.is_empty());
+    }
+}
+
+/*
+ * Helper functions for measuring how much of a pattern a corpus exercises.
+ */
+
+/**
+ * Tallies, over the lines of a corpus, how many lines each part of a
+ * pattern took part in a match on. The parts are the branches of every
+ * alternation and the bodies of every closure, which are the parts that
+ * a match can go without.
+ */
+#[derive(Debug)]
+pub struct PatternCoverage {
+    regex: Regex,
+    parts: Vec<CoveragePart>,
+    lines: usize,
+    matched: usize,
+}
+
+/**
+ * A part of a pattern, given as a span of char positions in the pattern
+ * text, and how many lines it took part in a match on.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoveragePart {
+    pub span: std::ops::Range<usize>,
+    pub lines: usize,
+    states: std::ops::Range<StateId>,
+}
+
+impl PatternCoverage {
+    /**
+     * Prepare to measure the coverage of a compiled pattern. Spans are
+     * measured on the pattern as written, so the Regex should not be
+     * case insensitive.
+     */
+    pub fn new(regex: Regex) -> PatternCoverage {
+        let mut parts = Vec::new();
+        coverage_parts(regex.ast(), 0, 1, false, &mut parts);
+        PatternCoverage {
+            regex,
+            parts,
+            lines: 0,
+            matched: 0,
+        }
+    }
+
+    /**
+     * Find every match of the pattern in a line, exactly as grep would,
+     * and count the line for each part that some match passed through.
+     */
+    pub fn add_line(&mut self, line: &str) {
+        self.lines += 1;
+        let nfa = self.regex.nfa();
+        let mut on_path = vec![false; nfa.states.len()];
+        let mut found = false;
+        for m in self.regex.find_iter(line) {
+            found = true;
+            for (id, on) in accepting_path_states(nfa, m.as_str()).iter().enumerate() {
+                on_path[id] |= *on;
+            }
+        }
+
+        if found {
+            self.matched += 1;
+            for part in self.parts.iter_mut() {
+                if part.states.clone().any(|id| on_path[id]) {
+                    part.lines += 1;
+                }
+            }
+        }
+    }
+
+    /**
+     * The number of lines seen so far.
+     */
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /**
+     * The number of lines seen so far that the pattern matched.
+     */
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+
+    /**
+     * The parts of the pattern, in the order they appear in it.
+     */
+    pub fn parts(&self) -> &[CoveragePart] {
+        &self.parts
+    }
+}
+
+/**
+ * Collect the optional parts of an AST whose text begins at char position
+ * pos and whose states begin at id, returning the width of its text and
+ * the number of states it is built from. The states of each node are
+ * added to the NFA in one contiguous run, children first, which is what
+ * lets them be traced back to the text.
+ */
+fn coverage_parts(
+    ast: &AST,
+    pos: usize,
+    id: StateId,
+    optional: bool,
+    parts: &mut Vec<CoveragePart>,
+) -> (usize, usize) {
+    let idx = parts.len();
+    let (width, states) = match ast {
+        AST::Char(c) if is_metachar(*c) => (2, 1),
+        AST::Char(_) | AST::AnyChar => (1, 1),
+        AST::Catenation(lhs, rhs) => {
+            let (lw, ls) = coverage_parts(lhs, pos, id, false, parts);
+            let (rw, rs) = coverage_parts(rhs, pos + lw, id + ls, false, parts);
+            (lw + rw, ls + rs)
+        }
+        AST::Alternation(lhs, rhs) => {
+            let (lw, ls) = coverage_parts(lhs, pos, id, true, parts);
+            // a chain of alternatives is a list of branches, not a nest
+            let nested = matches!(**rhs, AST::Alternation(_, _));
+            let (rw, rs) = coverage_parts(rhs, pos + lw + 1, id + ls, !nested, parts);
+            (lw + 1 + rw, ls + rs + 1)
+        }
+        AST::Closure(c) | AST::OneOrMore(c) => {
+            let (w, s) = coverage_parts(c, pos, id, matches!(ast, AST::Closure(_)), parts);
+            (w + 1, s + 1)
+        }
+        AST::Group(_, c) => {
+            let (w, s) = coverage_parts(c, pos + 1, id, false, parts);
+            (w + 2, s)
+        }
+        AST::Intersection(lhs, rhs) => {
+            let (lw, _) = coverage_parts(lhs, pos, id, false, &mut vec![]);
+            let (rw, _) = coverage_parts(rhs, pos, id, false, &mut vec![]);
+            (lw + 1 + rw, NFA::from_ast(ast).states.len() - 2)
+        }
+        AST::Complement(c) => {
+            let (w, _) = coverage_parts(c, pos, id, false, &mut vec![]);
+            (w + 1, NFA::from_ast(ast).states.len() - 2)
+        }
+    };
+    if optional {
+        parts.insert(
+            idx,
+            CoveragePart {
+                span: pos..pos + width,
+                lines: 0,
+                states: id..id + states,
+            },
+        );
+    }
+    (width, states)
+}
+
+/**
+ * Which states of the NFA lie on some path that accepts exactly the
+ * given input. A state at a position lies on such a path when it can be
+ * reached from the start by reading the input up to there, and the end
+ * can be reached from it by reading the rest.
+ */
+pub fn accepting_path_states(nfa: &NFA, input: &str) -> Vec<bool> {
+    let chars: Vec<char> = input.chars().collect();
+    let len = nfa.states.len();
+
+    // the states reached before reading each char, and after the last
+    let mut reached: Vec<Vec<bool>> = Vec::with_capacity(chars.len() + 1);
+    reached.push(epsilon_reach(nfa, &[nfa.start]));
+    for c in chars.iter() {
+        let targets: Vec<StateId> = reached
+            .last()
+            .unwrap_or(&vec![])
+            .iter()
+            .enumerate()
+            .filter_map(|(id, on)| match &nfa.states[id] {
+                Match(m, Some(next)) if *on && m.matches(*c) => Some(*next),
+                _ => None,
+            })
+            .collect();
+        reached.push(epsilon_reach(nfa, &targets));
+    }
+
+    // walk backwards, keeping the states that can still finish
+    let mut on_path = vec![false; len];
+    let mut finishing: Vec<bool> = vec![false; len];
+    for pos in (0..=chars.len()).rev() {
+        let mut current: Vec<bool> = (0..len)
+            .map(|id| match &nfa.states[id] {
+                End => pos == chars.len(),
+                Match(m, Some(next)) => {
+                    pos < chars.len() && m.matches(chars[pos]) && finishing[*next]
+                }
+                _ => false,
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in 0..len {
+                let finishes = match nfa.states[id] {
+                    Start(Some(next)) => current[next],
+                    Split(Some(lhs), Some(rhs)) => current[lhs] || current[rhs],
+                    _ => false,
+                };
+                if finishes && !current[id] {
+                    current[id] = true;
+                    changed = true;
+                }
+            }
+        }
+        for id in 0..len {
+            on_path[id] |= current[id] && reached[pos][id];
+        }
+        finishing = current;
+    }
+    on_path
+}
+
+/**
+ * Every state reachable from the given states by epsilon transitions,
+ * including the given states and the Start and Split states passed.
+ */
+fn epsilon_reach(nfa: &NFA, states: &[StateId]) -> Vec<bool> {
+    let mut reached = vec![false; nfa.states.len()];
+    let mut stack: Vec<StateId> = states.to_vec();
+    while let Some(id) = stack.pop() {
+        if reached[id] {
+            continue;
+        }
+        reached[id] = true;
+        match nfa.states[id] {
+            Start(Some(next)) => stack.push(next),
+            Split(Some(lhs), Some(rhs)) => {
+                stack.push(lhs);
+                stack.push(rhs);
+            }
+            _ => {}
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod pattern_coverage_tests {
+    use super::*;
+
+    fn spans(pattern: &str) -> Vec<&str> {
+        let coverage = PatternCoverage::new(Regex::new(pattern).unwrap());
+        let chars: Vec<char> = pattern.chars().collect();
+        coverage
+            .parts()
+            .iter()
+            .map(|part| {
+                let start: usize = chars[..part.span.start].iter().map(|c| c.len_utf8()).sum();
+                let end: usize = chars[..part.span.end].iter().map(|c| c.len_utf8()).sum();
+                &pattern[start..end]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parts() {
+        assert_eq!(spans("foo|ba(r|z)"), vec!["foo", "ba(r|z)", "r", "z"]);
+        assert_eq!(spans("a|b|c"), vec!["a", "b", "c"]);
+        assert_eq!(spans("x(ab)*y+"), vec!["(ab)"]);
+        assert_eq!(spans("((a|b)&~c)|d"), vec!["((a|b)&~c)", "d"]);
+        assert_eq!(spans("a\\&|\\~b"), vec!["a\\&", "\\~b"]);
+    }
+
+    #[test]
+    fn states_line_up() {
+        for pattern in ["a(b|c)*d+", "(x&.)|~(yz)", "((a)|b*)|(c+)"].iter() {
+            let ast =
+                crate::parser::Parser::parse_groups(crate::tokenizer::Tokenizer::new(pattern))
+                    .unwrap();
+            let (width, states) = coverage_parts(&ast, 0, 1, false, &mut vec![]);
+            assert_eq!(width, pattern.chars().count());
+            assert_eq!(states + 2, NFA::from_ast(&ast).states.len());
+        }
+    }
+
+    #[test]
+    fn path_states() {
+        let nfa = NFA::from("a(b|c)").unwrap();
+        let on_path = accepting_path_states(&nfa, "ac");
+        let b = nfa
+            .states
+            .iter()
+            .position(|s| matches!(s, Match(Char::Literal('b'), _)));
+        let c = nfa
+            .states
+            .iter()
+            .position(|s| matches!(s, Match(Char::Literal('c'), _)));
+        assert!(!on_path[b.unwrap()]);
+        assert!(on_path[c.unwrap()]);
+        assert!(!accepting_path_states(&nfa, "ad").iter().any(|on| *on));
+    }
+
+    #[test]
+    fn counts() {
+        let mut coverage = PatternCoverage::new(Regex::new("error|warning|fatal").unwrap());
+        for line in ["an error", "warning: x", "error again", "nothing"].iter() {
+            coverage.add_line(line);
+        }
+        assert_eq!(coverage.lines(), 4);
+        assert_eq!(coverage.matched(), 3);
+        let lines: Vec<usize> = coverage.parts().iter().map(|part| part.lines).collect();
+        assert_eq!(lines, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn closures() {
+        let mut coverage = PatternCoverage::new(Regex::new("ab*c").unwrap());
+        coverage.add_line("ac ac");
+        coverage.add_line("xabc");
+        assert_eq!(coverage.matched(), 2);
+        assert_eq!(coverage.parts()[0].lines, 1);
+    }
+
+    #[test]
+    fn searches_like_grep() {
+        let mut coverage = PatternCoverage::new(Regex::new(".*error.*&~(.*debug.*)|x").unwrap());
+        coverage.add_line("debug: an error");
+        assert_eq!(coverage.matched(), 0);
+        assert!(coverage.parts().iter().all(|part| part.lines == 0));
+        coverage.add_line("an error");
+        assert_eq!(coverage.matched(), 1);
+        assert_eq!(coverage.parts()[0].lines, 1);
+
+        let regex = crate::regex::RegexBuilder::new("ab|b")
+            .whole_word(true)
+            .build()
+            .unwrap();
+        let mut coverage = PatternCoverage::new(regex);
+        coverage.add_line("xab b");
+        let lines: Vec<usize> = coverage.parts().iter().map(|part| part.lines).collect();
+        assert_eq!(lines, vec![0, 1]);
+    }
+}
+
+/*
+ * Helper functions for explaining why an NFA found no match.
+ */
 
 /**
- * Generate a string of the internal structure of the NFA.
+ * How far a search of some input got before every thread died. The
+ * longest partial match runs from byte offset start to byte offset
+ * position, where the Match states still alive expected one of the
+ * chars described by expected but found the char at position instead,
+ * or found the input had ended when position is its length.
  */
-pub fn nfa_dump(nfa: &NFA) -> String {
-    let mut s = String::new();
-    for (id, state) in nfa.states.iter().enumerate() {
-        s.push_str(&format!("{:03} | {:?}\n", id, state));
-    }
-    s
+#[derive(Debug, PartialEq)]
+pub struct Diagnosis {
+    pub start: usize,
+    pub position: usize,
+    pub states: Vec<StateId>,
+    pub expected: Vec<String>,
 }
 
 /**
- * Generate a DOT structured string.
+ * Search the input for a match as grep would, and explain why there is
+ * none. Returns None when the NFA does match some substring of the input.
+ * The furthest position any thread that read at least one char got to is
+ * reported, or the start of the input when no thread got that far.
  */
-pub fn nfa_dot(nfa: &NFA) -> String {
-    let mut dot = String::from("digraph nfa {rankdir=LR; \n\tnode [shape = circle];\n");
-    //let mut dot = String::from("digraph nfa {rankdir=LR;\n");
-    for (id, state) in nfa.states.iter().enumerate() {
-        //     let concat = concat!("Start", id);
-        dot.push_str(&match state {
-            // Start(Some(next)) => format!("\tstart [shape=\"none\"]\n\tstart -> {}\n", next),
-            Start(Some(next)) => format!("\t{} [shape=\"diamond\", style=\"filled\", fillcolor=\"lightskyblue\", label=\"Start\"]\n\t{} -> {}\n", id, id, next),
-            Match(c, Some(next)) => format!("\t{} [style=\"filled\", fillcolor=\"palegreen2\"]{} -> {} [label=\"{}\"]\n", id, id, next, c),
-            Split(Some(lhs), Some(rhs)) => format!(
-                "\t{0} [style=\"filled\", fillcolor=\"plum\"]{0} -> {1} [label=\"ε\"]\n\t{0} -> {2} [label=\"ε\"]\n",
-                id, rhs, lhs
-            ),
-            End => format!("\t{} [shape=\"doublecircle\", style=\"filled\", fillcolor=\"lightsalmon\"]\n", id),
-            _ => String::new(),
-        });
+pub fn why_not(nfa: &NFA, input: &str) -> Option<Diagnosis> {
+    why_not_bounded(nfa, input, |_| true, |_| true)
+}
+
+/**
+ * Like `why_not`, but a match may only begin at a byte offset for which
+ * `starts` holds and only end at one for which `ends` holds, as with
+ * `NFA::find_at_bounded`.
+ */
+pub fn why_not_bounded<S, E>(nfa: &NFA, input: &str, starts: S, ends: E) -> Option<Diagnosis>
+where
+    S: Fn(usize) -> bool,
+    E: Fn(usize) -> bool,
+{
+    let end = nfa.states.len() - 1;
+
+    // Each thread is a Match or End state paired with the offset it began at
+    let mut threads: Vec<(StateId, usize)> = Vec::new();
+    let mut furthest: Option<(usize, Vec<(StateId, usize)>)> = None;
+    let mut first: Vec<(StateId, usize)> = Vec::new();
+    let mut chars = input.char_indices();
+
+    loop {
+        let (pos, c) = match chars.next() {
+            Some((idx, c)) => (idx, Some(c)),
+            None => (input.len(), None),
+        };
+
+        let progressed: Vec<(StateId, usize)> = threads
+            .iter()
+            .filter(|(_, origin)| *origin < pos)
+            .cloned()
+            .collect();
+        if !progressed.is_empty() {
+            furthest = Some((pos, progressed));
+        }
+        if starts(pos) {
+            for (id, on) in epsilon_reach(nfa, &[nfa.start]).iter().enumerate() {
+                let alive = matches!(nfa.states[id], Match(_, Some(_)) | End);
+                if *on && alive && !threads.iter().any(|(state, _)| *state == id) {
+                    threads.push((id, pos));
+                }
+            }
+        }
+        if pos == 0 {
+            first = threads.clone();
+        }
+        if ends(pos) && threads.iter().any(|(state, _)| *state == end) {
+            return None;
+        }
+
+        let c = match c {
+            Some(c) => c,
+            None => break,
+        };
+        let mut next_threads: Vec<(StateId, usize)> = Vec::new();
+        for (state, origin) in threads {
+            if let Match(m, Some(next)) = &nfa.states[state] {
+                if !m.matches(c) {
+                    continue;
+                }
+                for (id, on) in epsilon_reach(nfa, &[*next]).iter().enumerate() {
+                    let alive = matches!(nfa.states[id], Match(_, Some(_)) | End);
+                    if *on && alive {
+                        match next_threads.iter_mut().find(|(s, _)| *s == id) {
+                            Some(thread) => thread.1 = thread.1.min(origin),
+                            None => next_threads.push((id, origin)),
+                        }
+                    }
+                }
+            }
+        }
+        threads = next_threads;
+    }
+
+    let (position, alive) = furthest.unwrap_or((0, first));
+    let start = alive
+        .iter()
+        .map(|(_, origin)| *origin)
+        .min()
+        .unwrap_or(position);
+    let mut states: Vec<StateId> = alive.iter().map(|(state, _)| *state).collect();
+    states.sort();
+    let mut expected: Vec<String> = states
+        .iter()
+        .filter_map(|id| match &nfa.states[*id] {
+            Match(c, _) => Some(c.to_string()),
+            _ => None,
+        })
+        .collect();
+    expected.sort();
+    expected.dedup();
+    Some(Diagnosis {
+        start,
+        position,
+        states,
+        expected,
+    })
+}
+
+#[cfg(test)]
+mod why_not_tests {
+    use super::*;
+
+    #[test]
+    fn matches() {
+        assert_eq!(why_not(&NFA::from("ab").unwrap(), "xaby"), None);
+        assert_eq!(why_not(&NFA::from("x*").unwrap(), "abc"), None);
+    }
+
+    #[test]
+    fn wrong_char() {
+        let diagnosis = why_not(&NFA::from("abc").unwrap(), "xxabd").unwrap();
+        assert_eq!(diagnosis.start, 2);
+        assert_eq!(diagnosis.position, 4);
+        assert_eq!(diagnosis.expected, vec!["c"]);
+    }
+
+    #[test]
+    fn input_ends() {
+        let diagnosis = why_not(&NFA::from("ab(c|d)").unwrap(), "zab").unwrap();
+        assert_eq!(diagnosis.start, 1);
+        assert_eq!(diagnosis.position, 3);
+        assert_eq!(diagnosis.expected, vec!["c", "d"]);
+    }
+
+    #[test]
+    fn no_progress() {
+        let diagnosis = why_not(&NFA::from("a.|b").unwrap(), "xyz").unwrap();
+        assert_eq!(diagnosis.start, 0);
+        assert_eq!(diagnosis.position, 0);
+        assert_eq!(diagnosis.expected, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn bounded() {
+        let nfa = NFA::from("ab").unwrap();
+        let diagnosis = why_not_bounded(&nfa, "abc", |pos| pos == 0, |pos| pos == 3).unwrap();
+        assert_eq!(diagnosis.start, 0);
+        assert_eq!(diagnosis.position, 2);
+        assert!(diagnosis.expected.is_empty());
+        assert_eq!(
+            why_not_bounded(&nfa, "ab", |pos| pos == 0, |pos| pos == 2),
+            None
+        );
+    }
+
+    #[test]
+    fn multibyte() {
+        let diagnosis = why_not(&NFA::from("é.z").unwrap(), "aéb").unwrap();
+        assert_eq!(diagnosis.start, 1);
+        assert_eq!(diagnosis.position, 4);
+        assert_eq!(diagnosis.expected, vec!["z"]);
     }
-    dot += "}";
-    dot
 }
 
 /**
@@ -51,6 +2002,13 @@ impl std::fmt::Display for Char {
         match self {
             Char::Literal(c) => write!(f, "{}", c),
             Char::Any => write!(f, "ANY"),
+            Char::Except(excluded) => {
+                write!(f, "[^")?;
+                for c in excluded {
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "]")
+            }
         }
     }
 }
@@ -61,16 +2019,87 @@ use rand::prelude::*;
 /**
  * Generate a specified number of random strings that will be accepted by the given nfa. Takes in a
  * reference to an NFA and a usize which will be how many strings are generated.
- * Returns a Vec<String> containing all the generated strings.
+ * Returns a `Vec<String>` containing all the generated strings.
  */
 pub fn gen(nfa: &NFA, num: usize) -> Vec<String> {
+    gen_with(nfa, num, &GenOptions::default())
+}
+
+/**
+ * Options for generating random strings. A seed makes the strings the
+ * same on every run, max_len bounds how many chars each string has,
+ * unique leaves out strings that were already generated, and alphabet
+ * restricts the chars that `.` and complements are filled in with.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct GenOptions {
+    pub seed: Option<u64>,
+    pub max_len: Option<usize>,
+    pub unique: bool,
+    pub alphabet: Option<Vec<char>>,
+}
+
+impl GenOptions {
+    /**
+     * The random number generator the options call for.
+     */
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/**
+ * The chars random strings are drawn from when no alphabet is given:
+ * every letter and digit, which is what `gen` fills `.` and complements
+ * in with, plus every char the NFA's pattern names.
+ */
+pub fn gen_alphabet(nfa: &NFA) -> Vec<char> {
+    let mut alphabet = default_alphabet(nfa);
+    alphabet.extend(('a'..='z').chain('A'..='Z').chain('0'..='9'));
+    alphabet.sort();
+    alphabet.dedup();
+    alphabet
+}
+
+/**
+ * How many walks in a row may turn up only strings generated before
+ * a unique generator decides there are no new strings left.
+ */
+const MAX_REPEATS: usize = 1000;
+
+/**
+ * Generate a specified number of random strings accepted by the given nfa, following
+ * the given options. Fewer strings are returned when the nfa accepts nothing within
+ * the bounds, or when unique strings are asked for and they run out.
+ */
+pub fn gen_with(nfa: &NFA, num: usize, options: &GenOptions) -> Vec<String> {
     // create the vector that will contain the generated strings and that will be returned
     let mut strings: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut repeats = 0;
+    let mut rng = options.rng();
+    let alphabet = options.alphabet.as_deref();
+
+    // the fewest chars needed to get from each state to the end state, which
+    // steers the walk away from branches that cannot finish within max_len
+    let remaining = fewest_chars(nfa, alphabet);
+    let max_len = options.max_len.unwrap_or(usize::MAX);
+    let fits = |len: usize, id: StateId| match remaining[id] {
+        Some(needed) => needed <= max_len - len,
+        None => false,
+    };
+    if !fits(0, nfa.start) {
+        return strings;
+    }
 
     // loop until the vector contains the specified number of strings
-    while strings.len() < num {
+    while strings.len() < num && repeats < MAX_REPEATS {
         // make the string that will be built up to eventually become the accepted string
         let mut s = String::new();
+        let mut len = 0;
 
         // idx is the index of the current state in the nfa, start at 0 which is the start state
         let mut idx = 0;
@@ -81,19 +2110,16 @@ pub fn gen(nfa: &NFA, num: usize) -> Vec<String> {
             match &nfa.states[idx] {
                 Start(Some(id)) => idx = *id,
                 Match(c, Some(id)) => {
-                    // if c is a char literal, push that onto s
-                    if let Char::Literal(ch) = &c {
-                        s.push(*ch);
-                    } else {
-                        // otherwise push a random char since we can take any character
-                        s.push(thread_rng().sample(Alphanumeric));
-                    }
+                    // push the char literal, or a random char the label permits
+                    s.push(pick_char(c, alphabet, &mut rng).unwrap_or('?'));
+                    len += 1;
                     idx = *id;
                 }
                 Split(Some(lhs), Some(rhs)) => {
-                    // use random() to generate a random bool
-                    // in order to randomly decide which branch of the split to follow
-                    if random() {
+                    // randomly decide which branch of the split to follow, unless
+                    // only one of the branches can reach the end state in time
+                    let (lhs_fits, rhs_fits) = (fits(len, *lhs), fits(len, *rhs));
+                    if !rhs_fits || (lhs_fits && rng.gen()) {
                         idx = *lhs;
                     } else {
                         idx = *rhs;
@@ -102,15 +2128,21 @@ pub fn gen(nfa: &NFA, num: usize) -> Vec<String> {
                 End => {
                     // we've reached the end of the nfa
                     // so push the built-up string onto the strings vec
-                    strings.push(s);
+                    if !options.unique || seen.insert(s.clone()) {
+                        strings.push(s);
+                        repeats = 0;
+                    } else {
+                        repeats += 1;
+                    }
 
                     // made it through the nfa, so break out of the loop
                     break;
                 }
                 _ => {
-                    /* default case so match is comprehensive
-                    don't want to do anthing here
-                    since we should never reach this point */
+                    /* a state with no way forward only occurs when
+                    the nfa accepts nothing at all, so no strings
+                    can ever be generated */
+                    return strings;
                 }
             }
         }
@@ -119,10 +2151,455 @@ pub fn gen(nfa: &NFA, num: usize) -> Vec<String> {
     strings
 }
 
+/**
+ * The fewest chars that must be read to get from each state of the NFA
+ * to its end state, or None when it cannot get there. With an alphabet,
+ * only Match states that permit one of its chars can be passed through.
+ */
+fn fewest_chars(nfa: &NFA, alphabet: Option<&[char]>) -> Vec<Option<usize>> {
+    let mut fewest: Vec<Option<usize>> = nfa
+        .states
+        .iter()
+        .map(|s| if let End = s { Some(0) } else { None })
+        .collect();
+    let usable = |c: &Char| match alphabet {
+        Some(chars) => chars.iter().any(|ch| c.matches(*ch)),
+        None => true,
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (id, state) in nfa.states.iter().enumerate() {
+            let needed = match state {
+                Start(Some(next)) => fewest[*next],
+                Split(Some(lhs), Some(rhs)) => match (fewest[*lhs], fewest[*rhs]) {
+                    (Some(l), Some(r)) => Some(l.min(r)),
+                    (l, r) => l.or(r),
+                },
+                Match(c, Some(next)) if usable(c) => fewest[*next].map(|n| n + 1),
+                _ => None,
+            };
+            if needed.is_some() && (fewest[id].is_none() || needed < fewest[id]) {
+                fewest[id] = needed;
+                changed = true;
+            }
+        }
+    }
+    fewest
+}
+
+/**
+ * Generate the given number of random strings, drawn uniformly from all
+ * the strings made of chars from the alphabet, with a length in the
+ * given range, that the NFA accepts in full. Returns an error when there
+ * are too many such strings to count, and no strings when there are none.
+ */
+pub fn gen_uniform(
+    nfa: &NFA,
+    alphabet: &[char],
+    lengths: std::ops::RangeInclusive<usize>,
+    num: usize,
+) -> Result<Vec<String>, String> {
+    let options = GenOptions {
+        alphabet: Some(alphabet.to_vec()),
+        ..GenOptions::default()
+    };
+    gen_uniform_with(nfa, lengths, num, &options)
+}
+
+/**
+ * Generate strings uniformly by length like `gen_uniform`, following the
+ * given options. The alphabet defaults to `gen_alphabet`, and max_len is
+ * ignored in favour of the range of lengths.
+ */
+pub fn gen_uniform_with(
+    nfa: &NFA,
+    lengths: std::ops::RangeInclusive<usize>,
+    num: usize,
+    options: &GenOptions,
+) -> Result<Vec<String>, String> {
+    let alphabet = match &options.alphabet {
+        Some(alphabet) => alphabet.clone(),
+        None => gen_alphabet(nfa),
+    };
+    let mut rng = options.rng();
+    if !options.unique {
+        return sample_uniform(nfa, &alphabet, lengths, num, &mut rng);
+    }
+
+    let mut strings: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut repeats = 0;
+    while strings.len() < num && repeats < MAX_REPEATS {
+        let sample = sample_uniform(nfa, &alphabet, lengths.clone(), 1, &mut rng)?;
+        match sample.into_iter().next() {
+            Some(s) if seen.insert(s.clone()) => {
+                strings.push(s);
+                repeats = 0;
+            }
+            Some(_) => repeats += 1,
+            None => break,
+        }
+    }
+    Ok(strings)
+}
+
+/**
+ * Draw uniform samples using the given random number generator. Each
+ * sample is a random rank among all the strings in shortlex order, which
+ * is turned into a string by following the per-state counts: at each
+ * step, the ranks are split between the chars in alphabetical order by
+ * how many strings continue on from each.
+ */
+fn sample_uniform<R: Rng>(
+    nfa: &NFA,
+    alphabet: &[char],
+    lengths: std::ops::RangeInclusive<usize>,
+    num: usize,
+    rng: &mut R,
+) -> Result<Vec<String>, String> {
+    let dfa = nfa.to_dfa();
+    let mut alphabet = alphabet.to_vec();
+    alphabet.sort();
+    alphabet.dedup();
+    let counts = suffix_counts(&dfa, &alphabet, *lengths.end())?;
+
+    let too_many = || String::from("Too many accepted strings to sample uniformly");
+    let mut total: u128 = 0;
+    for len in lengths.clone() {
+        let count = counts[len][0].ok_or_else(too_many)?;
+        total = total.checked_add(count).ok_or_else(too_many)?;
+    }
+    if total == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut strings = Vec::with_capacity(num);
+    while strings.len() < num {
+        let mut rank = rng.gen_range(0, total);
+
+        // pick the length, then the string of that length
+        let mut len = *lengths.start();
+        while rank >= counts[len][0].unwrap_or(0) {
+            rank -= counts[len][0].unwrap_or(0);
+            len += 1;
+        }
+
+        let mut s = String::new();
+        let mut q = 0;
+        for remaining in (0..len).rev() {
+            for c in alphabet.iter() {
+                let next = dfa.step(q, *c);
+                let count = counts[remaining][next].unwrap_or(0);
+                if rank < count {
+                    s.push(*c);
+                    q = next;
+                    break;
+                }
+                rank -= count;
+            }
+        }
+        strings.push(s);
+    }
+    Ok(strings)
+}
+
+/**
+ * Generate a specified number of near-miss strings that the given nfa rejects, by
+ * taking accepted strings and inserting, deleting or substituting a random char.
+ * Mutants the nfa still accepts in full are thrown away. New chars come from the
+ * options' alphabet, or else the nfa's own, so that mutants stay close to the
+ * strings they came from.
+ */
+pub fn gen_negative(nfa: &NFA, num: usize, options: &GenOptions) -> Vec<String> {
+    let mut strings: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut repeats = 0;
+    let mut rng = options.rng();
+    let alphabet = match &options.alphabet {
+        Some(alphabet) => alphabet.clone(),
+        None => default_alphabet(nfa),
+    };
+
+    // the strings to mutate, where an nfa that accepts nothing has every
+    // string as a negative, starting from the empty one
+    let positives = GenOptions {
+        unique: false,
+        ..options.clone()
+    };
+    let mut positives = gen_with(nfa, num.max(1), &positives);
+    if positives.is_empty() {
+        positives.push(String::new());
+    }
+
+    while strings.len() < num && repeats < MAX_REPEATS {
+        let positive = &positives[strings.len() % positives.len()];
+        let mut chars: Vec<char> = positive.chars().collect();
+        let at = rng.gen_range(0, chars.len() + 1);
+        let c = *alphabet.choose(&mut rng).unwrap_or(&'?');
+        match rng.gen_range(0, 3) {
+            0 => chars.insert(at, c),
+            1 if at < chars.len() => {
+                chars.remove(at);
+            }
+            _ if at < chars.len() => chars[at] = c,
+            _ => chars.push(c),
+        }
+        let mutant: String = chars.into_iter().collect();
+
+        let fits = options
+            .max_len
+            .is_none_or(|max| mutant.chars().count() <= max);
+        if fits && !nfa.accepts_exactly(&mutant) && (!options.unique || seen.insert(mutant.clone()))
+        {
+            strings.push(mutant);
+            repeats = 0;
+        } else {
+            repeats += 1;
+        }
+    }
+    strings
+}
+
+/**
+ * Generate a specified number of strings that the given nfa rejects, drawn from its
+ * complement. Unlike `gen_negative`, every string the nfa rejects can turn up, not
+ * just near misses.
+ */
+pub fn gen_negative_exact(nfa: &NFA, num: usize, options: &GenOptions) -> Vec<String> {
+    gen_with(&nfa.complement(), num, options)
+}
+
+/**
+ * Find which states of the NFA can reach its end state.
+ */
+fn live_states(nfa: &NFA) -> Vec<bool> {
+    let ends = nfa.states.iter().map(|s| matches!(s, End)).collect();
+    mark_live(ends, |id| {
+        edges(&nfa.states[id]).into_iter().map(|(next, _)| next)
+    })
+}
+
+/**
+ * Pick a char that the given label matches. Literals produce themselves
+ * and every other label produces a random alphanumeric char it permits.
+ */
+fn random_char<R: Rng>(c: &Char, rng: &mut R) -> char {
+    match c {
+        Char::Literal(ch) => *ch,
+        _ => {
+            for _ in 0..64 {
+                let ch = rng.sample(Alphanumeric);
+                if c.matches(ch) {
+                    return ch;
+                }
+            }
+            // every alphanumeric char is excluded, so search further afield
+            c.example()
+        }
+    }
+}
+
+/**
+ * A random char permitted by a Match label, drawn from the alphabet when
+ * there is one. Returns None when no char of the alphabet is permitted.
+ */
+fn pick_char<R: Rng>(c: &Char, alphabet: Option<&[char]>, rng: &mut R) -> Option<char> {
+    match alphabet {
+        Some(chars) => {
+            let permitted: Vec<char> = chars.iter().cloned().filter(|ch| c.matches(*ch)).collect();
+            permitted.choose(rng).cloned()
+        }
+        None => Some(random_char(c, rng)),
+    }
+}
+
 #[cfg(test)]
 mod gen_tests {
     use super::*;
 
+    #[test]
+    fn gen_negative_rejected() {
+        let nfa = NFA::from("ab(c|d)+").unwrap();
+        let options = GenOptions {
+            seed: Some(11),
+            max_len: Some(8),
+            ..GenOptions::default()
+        };
+        let strings = gen_negative(&nfa, 30, &options);
+        assert_eq!(strings.len(), 30);
+        for st in &strings {
+            assert!(!nfa.accepts_exactly(st));
+            assert!(st.chars().all(|c| "abcde".contains(c)));
+        }
+    }
+
+    #[test]
+    fn gen_negative_of_everything() {
+        // every string is accepted, so there are no negatives to find
+        let nfa = NFA::from(".*").unwrap();
+        assert!(gen_negative(&nfa, 3, &GenOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn gen_negative_of_nothing() {
+        let nfa = NFA::from("a&b").unwrap();
+        let strings = gen_negative(&nfa, 3, &GenOptions::default());
+        assert_eq!(strings.len(), 3);
+    }
+
+    #[test]
+    fn gen_negative_exact_rejected() {
+        let nfa = NFA::from("a*b").unwrap();
+        let options = GenOptions {
+            seed: Some(5),
+            max_len: Some(4),
+            unique: true,
+            ..GenOptions::default()
+        };
+        let strings = gen_negative_exact(&nfa, 20, &options);
+        assert_eq!(strings.len(), 20);
+        for st in &strings {
+            assert!(!nfa.accepts_exactly(st));
+        }
+    }
+
+    #[test]
+    fn gen_with_seed() {
+        let nfa = NFA::from("(a|b)*c.").unwrap();
+        let options = GenOptions {
+            seed: Some(42),
+            ..GenOptions::default()
+        };
+        let first = gen_with(&nfa, 10, &options);
+        assert_eq!(first, gen_with(&nfa, 10, &options));
+        for st in &first {
+            assert!(nfa.accepts_exactly(st));
+        }
+    }
+
+    #[test]
+    fn gen_with_max_len() {
+        let nfa = NFA::from("a.*b*").unwrap();
+        let options = GenOptions {
+            seed: Some(1),
+            max_len: Some(3),
+            ..GenOptions::default()
+        };
+        let strings = gen_with(&nfa, 50, &options);
+        assert_eq!(strings.len(), 50);
+        for st in &strings {
+            assert!(nfa.accepts_exactly(st));
+            assert!(st.chars().count() <= 3);
+        }
+    }
+
+    #[test]
+    fn gen_with_max_len_too_short() {
+        let nfa = NFA::from("abc+").unwrap();
+        let options = GenOptions {
+            max_len: Some(2),
+            ..GenOptions::default()
+        };
+        assert!(gen_with(&nfa, 5, &options).is_empty());
+    }
+
+    #[test]
+    fn gen_with_unique() {
+        // only four strings fit: "", a, b and bb
+        let nfa = NFA::from("a|b*").unwrap();
+        let options = GenOptions {
+            seed: Some(3),
+            max_len: Some(2),
+            unique: true,
+            ..GenOptions::default()
+        };
+        let mut strings = gen_with(&nfa, 10, &options);
+        strings.sort();
+        assert_eq!(strings, vec!["", "a", "b", "bb"]);
+    }
+
+    #[test]
+    fn gen_with_alphabet() {
+        let nfa = NFA::from(".(.|x)*").unwrap();
+        let options = GenOptions {
+            max_len: Some(6),
+            alphabet: Some(vec!['x', 'y']),
+            ..GenOptions::default()
+        };
+        for st in gen_with(&nfa, 20, &options) {
+            assert!(!st.is_empty() && st.chars().all(|c| c == 'x' || c == 'y'));
+        }
+    }
+
+    #[test]
+    fn gen_uniform_with_unique() {
+        let nfa = NFA::from("(a|b)(a|b)").unwrap();
+        let options = GenOptions {
+            seed: Some(9),
+            unique: true,
+            ..GenOptions::default()
+        };
+        let mut strings = gen_uniform_with(&nfa, 2..=2, 10, &options).unwrap();
+        strings.sort();
+        assert_eq!(strings, vec!["aa", "ab", "ba", "bb"]);
+    }
+
+    #[test]
+    fn gen_uniform_lengths() {
+        let nfa = NFA::from("(a|b)*c").unwrap();
+        let strings = gen_uniform(&nfa, &['a', 'b', 'c'], 3..=5, 20).unwrap();
+        assert_eq!(strings.len(), 20);
+        for st in &strings {
+            assert!(nfa.accepts_exactly(st));
+            assert!(st.len() >= 3 && st.len() <= 5);
+        }
+    }
+
+    #[test]
+    fn gen_uniform_distribution() {
+        // a|b* accepts four strings of length at most 2: "", a, b and bb
+        let nfa = NFA::from("a|b*").unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let strings = sample_uniform(&nfa, &['a', 'b'], 0..=2, 4000, &mut rng).unwrap();
+        for expected in ["", "a", "b", "bb"].iter() {
+            let seen = strings.iter().filter(|s| s == expected).count();
+            assert!(
+                seen > 850 && seen < 1150,
+                "{:?} seen {} times",
+                expected,
+                seen
+            );
+        }
+    }
+
+    #[test]
+    fn gen_uniform_default_alphabet() {
+        let nfa = NFA::from("a.b").unwrap();
+        let options = GenOptions {
+            seed: Some(3),
+            ..GenOptions::default()
+        };
+        let strings = gen_uniform_with(&nfa, 3..=3, 20, &options).unwrap();
+        let middles: HashSet<char> = strings.iter().filter_map(|s| s.chars().nth(1)).collect();
+        assert!(middles.len() > 5, "{:?}", strings);
+        assert_eq!(gen_alphabet(&NFA::from("x-y").unwrap()).len(), 63);
+    }
+
+    #[test]
+    fn gen_uniform_empty() {
+        let nfa = NFA::from("abc").unwrap();
+        assert_eq!(gen_uniform(&nfa, &['a', 'b', 'c'], 4..=8, 3), Ok(vec![]));
+    }
+
+    #[test]
+    fn gen_uniform_too_many() {
+        let nfa = NFA::from(".*").unwrap();
+        let alphabet: Vec<char> = ('a'..='z').collect();
+        assert!(gen_uniform(&nfa, &alphabet, 100..=100, 1).is_err());
+    }
+
     #[test]
     fn gen_4_a() {
         let nfa = NFA::from("a").unwrap();
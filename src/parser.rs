@@ -21,6 +21,9 @@ pub enum AST {
     Char(char),
     OneOrMore(Box<AST>),
     AnyChar,
+    Group(usize, Box<AST>),
+    Intersection(Box<AST>, Box<AST>),
+    Complement(Box<AST>),
 }
 
 /* Helper factory functions for building ASTs */
@@ -48,16 +51,43 @@ pub fn ast_one_or_more(val: AST) -> AST {
     AST::OneOrMore(Box::new(val))
 }
 
+pub fn ast_group(idx: usize, val: AST) -> AST {
+    AST::Group(idx, Box::new(val))
+}
+
+pub fn ast_intersection(lhs: AST, rhs: AST) -> AST {
+    AST::Intersection(Box::new(lhs), Box::new(rhs))
+}
+
+pub fn ast_complement(val: AST) -> AST {
+    AST::Complement(Box::new(val))
+}
+
 /* == End Syntax Tree Elements == */
 
 pub struct Parser<'tokens> {
     tokens: Peekable<Tokenizer<'tokens>>,
+    // The index of the next capture group, if groups are being kept
+    groups: Option<usize>,
 }
 
 impl<'tokens> Parser<'tokens> {
     pub fn parse(tokenizer: Tokenizer<'tokens>) -> Result<AST, String> {
+        Parser::parse_with(tokenizer, None)
+    }
+
+    /**
+     * Parse like `parse`, but wrap every parenthesized expression in a
+     * Group numbered by the position of its LParen, starting from 1.
+     */
+    pub fn parse_groups(tokenizer: Tokenizer<'tokens>) -> Result<AST, String> {
+        Parser::parse_with(tokenizer, Some(1))
+    }
+
+    fn parse_with(tokenizer: Tokenizer<'tokens>, groups: Option<usize>) -> Result<AST, String> {
         let mut parser = Parser {
             tokens: tokenizer.peekable(),
+            groups,
         };
 
         // Parse, and then ensure that all tokens in the expr were used.
@@ -148,6 +178,68 @@ mod public_api {
     }
 
     #[test]
+    fn parse_groups() {
+        let res = Parser::parse_groups(Tokenizer::new("(a(b))|(c)")).unwrap();
+        assert_eq!(
+            ast_alternation(
+                ast_group(
+                    1,
+                    ast_catenation(ast_char('a'), ast_group(2, ast_char('b')))
+                ),
+                ast_group(3, ast_char('c'))
+            ),
+            res
+        );
+    }
+
+    #[test]
+    fn parse_intersection_precedence() {
+        let res = Parser::parse(Tokenizer::new("ab&c|d")).unwrap();
+        assert_eq!(
+            ast_alternation(
+                ast_intersection(ast_catenation(ast_char('a'), ast_char('b')), ast_char('c')),
+                ast_char('d')
+            ),
+            res
+        );
+    }
+
+    #[test]
+    fn parse_complement() {
+        let res = Parser::parse(Tokenizer::new(".*e.*&~(.*d.*)")).unwrap();
+        let any_star = || ast_closure(ast_any_char());
+        assert_eq!(
+            ast_intersection(
+                ast_catenation(any_star(), ast_catenation(ast_char('e'), any_star())),
+                ast_complement(ast_catenation(
+                    any_star(),
+                    ast_catenation(ast_char('d'), any_star())
+                ))
+            ),
+            res
+        );
+    }
+
+    #[test]
+    fn parse_complement_precedence() {
+        let res = Parser::parse(Tokenizer::new("a~b*c")).unwrap();
+        assert_eq!(
+            ast_catenation(
+                ast_char('a'),
+                ast_catenation(ast_complement(ast_closure(ast_char('b'))), ast_char('c'))
+            ),
+            res
+        );
+    }
+
+    #[test]
+    fn parse_intersection_err() {
+        let res = Parser::parse(Tokenizer::new("a&"));
+        assert_eq!(Err(String::from("Unexpected end of input")), res);
+    }
+
+    #[test]
+    #[allow(clippy::useless_format)]
     fn parse_err() {
         let res = Parser::parse(Tokenizer::new("(a))"));
         assert_eq!(Err(format!("Expected end of input, found RParen")), res);
@@ -158,10 +250,11 @@ mod public_api {
  * Internal-only parser methods to process grammar through recursive descent.
  */
 
+#[allow(clippy::empty_line_after_doc_comments)]
 impl<'tokens> Parser<'tokens> {
-    // RegExpr -> <Catenation> (UnionBar <RegExpr>)?
+    // RegExpr -> <Intersection> (UnionBar <RegExpr>)?
     fn reg_expr(&mut self) -> Result<AST, String> {
-        let lhs = self.catenation()?;
+        let lhs = self.intersection()?;
 
         // If UnionBar, return Alternation Result
         // Peek because going to take later on in other methods
@@ -183,6 +276,41 @@ impl<'tokens> Parser<'tokens> {
         Ok(ast_alternation(lhs, rhs))
     }
 
+    // Intersection -> <Catenation> (IntersectionAmp <Intersection>)?
+    fn intersection(&mut self) -> Result<AST, String> {
+        let lhs = self.catenation()?;
+
+        // If IntersectionAmp, return Intersection Result
+        if let Some(Token::IntersectionAmp) = self.tokens.peek() {
+            self.handle_intersection_amp(lhs)
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    // Consume IntersectionAmp token, get intersection from right hand side
+    // Return an Intersection Result with lhs and rhs
+    fn handle_intersection_amp(&mut self, lhs: AST) -> Result<AST, String> {
+        self.take_next_token()?;
+        let rhs = self.intersection()?;
+        Ok(ast_intersection(lhs, rhs))
+    }
+
+    // Complement -> ComplementTilde <Complement> | <Closure>
+    fn complement(&mut self) -> Result<AST, String> {
+        if let Some(Token::ComplementTilde) = self.tokens.peek() {
+            self.handle_complement_tilde()
+        } else {
+            self.kleene()
+        }
+    }
+
+    // Consume ComplementTilde token, return Complement Result with operand
+    fn handle_complement_tilde(&mut self) -> Result<AST, String> {
+        self.take_next_token()?;
+        Ok(ast_complement(self.complement()?))
+    }
+
     // Atom -> LParen <RegExpr> RParen | AnyChar | Char
     fn atom(&mut self) -> Result<AST, String> {
         let t = self.take_next_token()?;
@@ -208,11 +336,18 @@ impl<'tokens> Parser<'tokens> {
     }
 
     // Get regex inside parens, consume RParen
-    // Return a Result with regex
+    // Return a Result with regex, wrapped in a Group if groups are kept
     fn handle_parens(&mut self) -> Result<AST, String> {
+        let group = self.groups;
+        if let Some(idx) = group {
+            self.groups = Some(idx + 1);
+        }
         let express = self.reg_expr()?;
         self.consume_token(Token::RParen)?;
-        Ok(express)
+        match group {
+            Some(idx) => Ok(ast_group(idx, express)),
+            None => Ok(express),
+        }
     }
 
     // Closure -> <Atom> KleeneStar? KleenePlus?
@@ -245,17 +380,19 @@ impl<'tokens> Parser<'tokens> {
         Ok(ast_one_or_more(atom))
     }
 
-    // Catenation -> <Closure> <Catenation>?
+    // Catenation -> <Complement> <Catenation>?
     fn catenation(&mut self) -> Result<AST, String> {
-        // Take the Closure
-        let closure = self.kleene()?;
+        // Take the Closure, or its Complement
+        let closure = self.complement()?;
 
         // Peek for LParen, AnyChar, Char
         // If match is found, give back a Catenation Result
         // If no match is found, give back a Closure Result
         if let Some(t) = self.tokens.peek() {
             match t {
-                Token::LParen | Token::AnyChar | Token::Char(_) => self.handle_catenation(closure),
+                Token::LParen | Token::AnyChar | Token::Char(_) | Token::ComplementTilde => {
+                    self.handle_catenation(closure)
+                }
                 _ => Ok(closure),
             }
         } else {
@@ -274,6 +411,7 @@ impl<'tokens> Parser<'tokens> {
  */
 
 #[cfg(test)]
+#[allow(clippy::empty_line_after_doc_comments)]
 mod private_api {
     use super::*;
 
@@ -306,6 +444,7 @@ mod private_api {
         }
 
         #[test]
+        #[allow(clippy::useless_format)]
         fn atom_parens_err() {
             assert_eq!(
                 Parser::from("(").atom(),
@@ -384,6 +523,45 @@ mod private_api {
         }
     }
 
+    mod lvl2_5 {
+        use super::*;
+
+        #[test]
+        fn complement_closure() {
+            assert_eq!(
+                Parser::from("~a*").complement().unwrap(),
+                ast_complement(ast_closure(ast_char('a')))
+            );
+        }
+
+        #[test]
+        fn complement_nested() {
+            assert_eq!(
+                Parser::from("~~a").complement().unwrap(),
+                ast_complement(ast_complement(ast_char('a')))
+            );
+        }
+
+        #[test]
+        fn intersection_cat() {
+            assert_eq!(
+                Parser::from("ab&c").intersection().unwrap(),
+                ast_intersection(ast_catenation(ast_char('a'), ast_char('b')), ast_char('c'))
+            );
+        }
+
+        #[test]
+        fn intersection_chain() {
+            assert_eq!(
+                Parser::from("a&b&c").intersection().unwrap(),
+                ast_intersection(
+                    ast_char('a'),
+                    ast_intersection(ast_char('b'), ast_char('c'))
+                )
+            );
+        }
+    }
+
     mod lvl3 {
         use super::*;
 
@@ -443,7 +621,6 @@ mod private_api {
             );
         }
     }
-
 }
 
 /* Parser's Helper Methods to improve ergonomics of parsing */
@@ -452,9 +629,11 @@ impl<'tokens> Parser<'tokens> {
      * Static helper method used in unit tests to establish a
      * parser given a string.
      */
+    #[cfg(test)]
     fn from(input: &'tokens str) -> Parser<'tokens> {
         Parser {
             tokens: Tokenizer::new(input).peekable(),
+            groups: None,
         }
     }
 
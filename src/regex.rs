@@ -0,0 +1,692 @@
+use super::nfa::helpers::{why_not_bounded, Diagnosis};
+use super::nfa::NFA;
+use super::parser::{ast_alternation, ast_char, Parser, AST};
+use super::tokenizer::Tokenizer;
+use std::fmt;
+
+/*
+ * thegrep - Tar Heel Extended Global Regular Expression Print
+ *
+ * Author(s): Alana Fiordalisi, Madison Huber
+ * ONYEN(s): fiordali, hubermm
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff and partner.
+ */
+
+/*
+ * ===== Public API =====
+ */
+
+/**
+ * A compiled regular expression that can be searched for anywhere
+ * within a string. Matches are leftmost-longest, as in egrep. A pattern
+ * that is an intersection or complement at its top level, or has one
+ * as a top-level alternative, describes whole lines, so it only ever
+ * matches the entire text, as if built with `whole_line`. Nested inside
+ * anything else, `&` and `~` are ordinary sub-languages.
+ */
+#[derive(Debug)]
+pub struct Regex {
+    pattern: String,
+    ast: AST,
+    nfa: NFA,
+    whole_line: bool,
+    whole_word: bool,
+}
+
+/**
+ * The errors that can occur while compiling a Regex.
+ */
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Syntax(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Regex {
+    /**
+     * Compile a pattern with the default options.
+     */
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        RegexBuilder::new(pattern).build()
+    }
+
+    /**
+     * The pattern this Regex was compiled from.
+     */
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /**
+     * The compiled NFA, for use with the helper functions in `nfa::helpers`.
+     */
+    pub fn nfa(&self) -> &NFA {
+        &self.nfa
+    }
+
+    /**
+     * The parsed pattern the NFA was built from.
+     */
+    pub(crate) fn ast(&self) -> &AST {
+        &self.ast
+    }
+
+    /**
+     * Whether the pattern matches anywhere within the text.
+     */
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /**
+     * The leftmost-longest match within the text, if any.
+     */
+    pub fn find<'t>(&self, text: &'t str) -> Option<Match<'t>> {
+        self.find_at(text, 0)
+    }
+
+    /**
+     * The leftmost-longest match beginning at or after byte offset `start`.
+     */
+    pub fn find_at<'t>(&self, text: &'t str, start: usize) -> Option<Match<'t>> {
+        let (starts, ends) = self.bounds(text);
+        self.nfa
+            .find_at_bounded(text, start, starts, ends)
+            .map(|(start, end)| Match { text, start, end })
+    }
+
+    /**
+     * Explain why the pattern does not match anywhere within the text,
+     * searching exactly as `find` does. Returns None when it matches.
+     */
+    pub fn why_not(&self, text: &str) -> Option<Diagnosis> {
+        let (starts, ends) = self.bounds(text);
+        why_not_bounded(&self.nfa, text, starts, ends)
+    }
+
+    /**
+     * An iterator over every successive non-overlapping match in the text.
+     */
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            regex: self,
+            text,
+            pos: 0,
+            last_end: None,
+        }
+    }
+
+    /**
+     * The leftmost-longest match within the text along with the spans
+     * of its capture groups. Groups are numbered by their opening paren
+     * from 1, with group 0 being the whole match. When a group could
+     * span the match in several ways, the leftmost greedy one is chosen.
+     */
+    pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
+        let found = self.find(text)?;
+        let chars: Vec<(usize, char)> = text[found.start..found.end]
+            .char_indices()
+            .map(|(idx, c)| (found.start + idx, c))
+            .collect();
+        let mut groups = vec![None; group_count(&self.ast) + 1];
+        groups[0] = Some((found.start, found.end));
+
+        let mut spans = Spans {
+            chars: &chars,
+            end: found.end,
+            groups: &mut groups,
+        };
+        spans.matches(&self.ast, 0, &Next::Done);
+
+        Some(Captures { text, groups })
+    }
+}
+
+/**
+ * Configures and compiles a Regex.
+ */
+#[derive(Debug)]
+pub struct RegexBuilder {
+    pattern: String,
+    case_insensitive: bool,
+    whole_line: bool,
+    whole_word: bool,
+}
+
+impl RegexBuilder {
+    pub fn new(pattern: &str) -> RegexBuilder {
+        RegexBuilder {
+            pattern: String::from(pattern),
+            case_insensitive: false,
+            whole_line: false,
+            whole_word: false,
+        }
+    }
+
+    /**
+     * Match letters regardless of their case.
+     */
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /**
+     * Only match whole lines, as if the pattern were anchored at both
+     * ends of the text.
+     */
+    pub fn whole_line(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.whole_line = yes;
+        self
+    }
+
+    /**
+     * Only match text that is neither preceded nor followed by a word
+     * char (a letter, digit or underscore). A match that fails this is
+     * retried shorter from the same position, then from later ones, so
+     * `a.*c` finds `abc` in `abc-cd`, while `a.*` finds nothing in `ba`.
+     */
+    pub fn whole_word(&mut self, yes: bool) -> &mut RegexBuilder {
+        self.whole_word = yes;
+        self
+    }
+
+    /**
+     * Compile the pattern with the configured options.
+     */
+    pub fn build(&self) -> Result<Regex, Error> {
+        let mut ast = Parser::parse_groups(Tokenizer::new(&self.pattern)).map_err(Error::Syntax)?;
+        if self.case_insensitive {
+            ast = fold_case(ast);
+        }
+        let nfa = NFA::from_ast(&ast);
+        let whole_line = self.whole_line || is_set_operation(&ast);
+        Ok(Regex {
+            pattern: self.pattern.clone(),
+            ast,
+            nfa,
+            whole_line,
+            whole_word: self.whole_word,
+        })
+    }
+}
+
+/**
+ * A single match of a Regex, identified by its byte offsets in the text.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Match<'t> {
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl<'t> Match<'t> {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    pub fn as_str(&self) -> &'t str {
+        &self.text[self.start..self.end]
+    }
+}
+
+/**
+ * An iterator over the non-overlapping matches of a Regex. An empty
+ * match directly after the previous match is skipped so that every
+ * step makes progress.
+ */
+pub struct Matches<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    pos: usize,
+    last_end: Option<usize>,
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Match<'t>> {
+        loop {
+            if self.pos > self.text.len() {
+                return None;
+            }
+            let found = self.regex.find_at(self.text, self.pos)?;
+            if found.start == found.end {
+                // Step past the next char so the search cannot stall
+                self.pos = match self.text[found.end..].chars().next() {
+                    Some(c) => found.end + c.len_utf8(),
+                    None => self.text.len() + 1,
+                };
+                if self.last_end == Some(found.end) {
+                    continue;
+                }
+            } else {
+                self.pos = found.end;
+            }
+            self.last_end = Some(found.end);
+            return Some(found);
+        }
+    }
+}
+
+/**
+ * The spans of a match's capture groups, indexed by group number.
+ */
+#[derive(Debug)]
+pub struct Captures<'t> {
+    text: &'t str,
+    groups: Vec<Option<(usize, usize)>>,
+}
+
+impl<'t> Captures<'t> {
+    /**
+     * The match of a group, or None if it did not take part in the match.
+     */
+    pub fn get(&self, idx: usize) -> Option<Match<'t>> {
+        match self.groups.get(idx) {
+            Some(Some((start, end))) => Some(Match {
+                text: self.text,
+                start: *start,
+                end: *end,
+            }),
+            _ => None,
+        }
+    }
+
+    /**
+     * The number of groups, including group 0.
+     */
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+/*
+ * ===== Internal API =====
+ */
+
+/**
+ * Which byte offsets of a text a match may begin and end at.
+ */
+type Bounds<'t> = (
+    Box<dyn Fn(usize) -> bool + 't>,
+    Box<dyn Fn(usize) -> bool + 't>,
+);
+
+impl Regex {
+    /**
+     * Where a match may begin and end within the text, given the
+     * whole-line and whole-word options.
+     */
+    fn bounds<'t>(&self, text: &'t str) -> Bounds<'t> {
+        if self.whole_line {
+            (
+                Box::new(|pos| pos == 0),
+                Box::new(move |pos| pos == text.len()),
+            )
+        } else if self.whole_word {
+            (
+                Box::new(move |pos| !is_word_char(text[..pos].chars().next_back())),
+                Box::new(move |pos| !is_word_char(text[pos..].chars().next())),
+            )
+        } else {
+            (Box::new(|_| true), Box::new(|_| true))
+        }
+    }
+}
+
+/**
+ * Rewrite every cased Char of an AST as an Alternation of its lower
+ * and upper case forms.
+ */
+fn fold_case(ast: AST) -> AST {
+    match ast {
+        AST::Char(c) => {
+            let lower: Vec<char> = c.to_lowercase().collect();
+            let upper: Vec<char> = c.to_uppercase().collect();
+            match (lower.as_slice(), upper.as_slice()) {
+                ([l], [u]) if l != u => ast_alternation(ast_char(*l), ast_char(*u)),
+                _ => ast_char(c),
+            }
+        }
+        AST::Alternation(lhs, rhs) => {
+            AST::Alternation(Box::new(fold_case(*lhs)), Box::new(fold_case(*rhs)))
+        }
+        AST::Catenation(lhs, rhs) => {
+            AST::Catenation(Box::new(fold_case(*lhs)), Box::new(fold_case(*rhs)))
+        }
+        AST::Closure(c) => AST::Closure(Box::new(fold_case(*c))),
+        AST::OneOrMore(c) => AST::OneOrMore(Box::new(fold_case(*c))),
+        AST::Group(idx, c) => AST::Group(idx, Box::new(fold_case(*c))),
+        AST::Intersection(lhs, rhs) => {
+            AST::Intersection(Box::new(fold_case(*lhs)), Box::new(fold_case(*rhs)))
+        }
+        AST::Complement(c) => AST::Complement(Box::new(fold_case(*c))),
+        AST::AnyChar => AST::AnyChar,
+    }
+}
+
+/**
+ * The highest group number used in an AST.
+ */
+fn group_count(ast: &AST) -> usize {
+    match ast {
+        AST::Alternation(lhs, rhs) | AST::Catenation(lhs, rhs) | AST::Intersection(lhs, rhs) => {
+            group_count(lhs).max(group_count(rhs))
+        }
+        AST::Closure(c) | AST::OneOrMore(c) | AST::Complement(c) => group_count(c),
+        AST::Group(idx, c) => group_count(c).max(*idx),
+        AST::Char(_) | AST::AnyChar => 0,
+    }
+}
+
+/**
+ * Whether an AST is an intersection or complement, looking through
+ * groups and into each branch of an alternation.
+ */
+fn is_set_operation(ast: &AST) -> bool {
+    match ast {
+        AST::Intersection(_, _) | AST::Complement(_) => true,
+        AST::Alternation(lhs, rhs) => is_set_operation(lhs) || is_set_operation(rhs),
+        AST::Group(_, c) => is_set_operation(c),
+        _ => false,
+    }
+}
+
+/**
+ * What remains to be matched once an AST node has matched, kept as a
+ * linked list on the stack of the backtracking matcher.
+ */
+enum Next<'a> {
+    Done,
+    Then(&'a AST, &'a Next<'a>),
+    // Close a group opened at the given char index
+    Close(usize, usize, &'a Next<'a>),
+    // Repeat a closure whose last iteration began at the given char index
+    Repeat(&'a AST, usize, &'a Next<'a>),
+    // Succeed only at the given char index, without going any further
+    Stop(usize),
+}
+
+/**
+ * A backtracking matcher that assigns group spans within a known match.
+ */
+struct Spans<'c> {
+    chars: &'c [(usize, char)],
+    end: usize,
+    groups: &'c mut Vec<Option<(usize, usize)>>,
+}
+
+impl<'c> Spans<'c> {
+    /**
+     * Match an AST node at a char index, then continue with `next`.
+     */
+    fn matches(&mut self, ast: &AST, pos: usize, next: &Next) -> bool {
+        match ast {
+            AST::Char(c) => match self.chars.get(pos) {
+                Some((_, ch)) if ch == c => self.resume(next, pos + 1),
+                _ => false,
+            },
+            AST::AnyChar => pos < self.chars.len() && self.resume(next, pos + 1),
+            AST::Catenation(lhs, rhs) => self.matches(lhs, pos, &Next::Then(rhs, next)),
+            AST::Alternation(lhs, rhs) => {
+                self.matches(lhs, pos, next) || self.matches(rhs, pos, next)
+            }
+            AST::Closure(c) => {
+                self.matches(c, pos, &Next::Repeat(c, pos, next)) || self.resume(next, pos)
+            }
+            AST::OneOrMore(c) => self.matches(c, pos, &Next::Repeat(c, pos, next)),
+            AST::Group(idx, c) => self.matches(c, pos, &Next::Close(*idx, pos, next)),
+            AST::Intersection(lhs, rhs) => {
+                let saved = self.groups.clone();
+                for stop in (pos..=self.chars.len()).rev() {
+                    if self.matches(lhs, pos, &Next::Stop(stop))
+                        && self.matches(rhs, pos, &Next::Stop(stop))
+                        && self.resume(next, stop)
+                    {
+                        return true;
+                    }
+                    self.groups.clone_from(&saved);
+                }
+                false
+            }
+            AST::Complement(c) => {
+                // Groups inside a complement never take part in a match
+                let saved = self.groups.clone();
+                for stop in (pos..=self.chars.len()).rev() {
+                    let matched = self.matches(c, pos, &Next::Stop(stop));
+                    self.groups.clone_from(&saved);
+                    if !matched && self.resume(next, stop) {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /**
+     * Continue matching with whatever remains after reaching a char index.
+     */
+    fn resume(&mut self, next: &Next, pos: usize) -> bool {
+        match next {
+            Next::Done => pos == self.chars.len(),
+            Next::Stop(stop) => pos == *stop,
+            Next::Then(ast, next) => self.matches(ast, pos, next),
+            Next::Close(idx, start, next) => {
+                let saved = self.groups[*idx];
+                self.groups[*idx] = Some((self.offset(*start), self.offset(pos)));
+                if self.resume(next, pos) {
+                    true
+                } else {
+                    self.groups[*idx] = saved;
+                    false
+                }
+            }
+            Next::Repeat(c, start, next) => {
+                // An iteration that consumed nothing must not loop again
+                if pos == *start {
+                    self.resume(next, pos)
+                } else {
+                    self.matches(c, pos, &Next::Repeat(c, pos, next)) || self.resume(next, pos)
+                }
+            }
+        }
+    }
+
+    /**
+     * The byte offset of a char index.
+     */
+    fn offset(&self, pos: usize) -> usize {
+        match self.chars.get(pos) {
+            Some((offset, _)) => *offset,
+            None => self.end,
+        }
+    }
+}
+
+/**
+ * Whether a char is part of a word for `whole_word`. The ends of the
+ * text, given as None, are not.
+ */
+fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod regex_tests {
+    use super::*;
+
+    #[test]
+    fn is_match_anywhere() {
+        let re = Regex::new("ab*c").unwrap();
+        assert!(re.is_match("xxabbbcxx"));
+        assert!(!re.is_match("xxabbbxx"));
+    }
+
+    #[test]
+    fn syntax_error() {
+        let err = Regex::new("(a").unwrap_err();
+        assert_eq!(err, Error::Syntax(String::from("Unexpected end of input")));
+        assert_eq!(err.to_string(), "Unexpected end of input");
+    }
+
+    #[test]
+    fn find_span() {
+        let re = Regex::new("b+").unwrap();
+        let m = re.find("abbbc").unwrap();
+        assert_eq!((m.start(), m.end()), (1, 4));
+        assert_eq!(m.as_str(), "bbb");
+    }
+
+    #[test]
+    fn find_iter_all() {
+        let re = Regex::new("a+|c").unwrap();
+        let found: Vec<&str> = re.find_iter("aabaca").map(|m| m.as_str()).collect();
+        assert_eq!(found, vec!["aa", "a", "c", "a"]);
+    }
+
+    #[test]
+    fn find_iter_empty() {
+        let re = Regex::new("a*").unwrap();
+        let found: Vec<(usize, usize)> =
+            re.find_iter("baab").map(|m| (m.start(), m.end())).collect();
+        assert_eq!(found, vec![(0, 0), (1, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn captures_groups() {
+        let re = Regex::new("(a+)(b|c)*d").unwrap();
+        let caps = re.captures("xaabcbd").unwrap();
+        assert_eq!(caps.len(), 3);
+        assert_eq!(caps.get(0).unwrap().as_str(), "aabcbd");
+        assert_eq!(caps.get(1).unwrap().as_str(), "aa");
+        assert_eq!(caps.get(2).unwrap().as_str(), "b");
+    }
+
+    #[test]
+    fn captures_unmatched_group() {
+        let re = Regex::new("(a)|(b)").unwrap();
+        let caps = re.captures("b").unwrap();
+        assert_eq!(caps.get(1), None);
+        assert_eq!(caps.get(2).unwrap().as_str(), "b");
+    }
+
+    #[test]
+    fn captures_nested() {
+        let re = Regex::new("((a)(b*))c").unwrap();
+        let caps = re.captures("abbc").unwrap();
+        assert_eq!(caps.get(1).unwrap().as_str(), "abb");
+        assert_eq!(caps.get(2).unwrap().as_str(), "a");
+        assert_eq!(caps.get(3).unwrap().as_str(), "bb");
+    }
+
+    #[test]
+    fn intersection_and_complement() {
+        let re = Regex::new("(.*error.*)&~(.*debug.*)").unwrap();
+        assert!(re.is_match("an error occurred"));
+        assert!(!re.is_match("a debug message"));
+        // The complement constrains the whole line, not just a match in it
+        assert!(!re.is_match("debug: an error occurred"));
+        assert_eq!(re.find("an error!").unwrap().range(), 0..9);
+        assert!(Regex::new("~(b)").unwrap().is_match("abc"));
+        assert!(!Regex::new("a&a").unwrap().is_match("ab"));
+        let caps = re.captures("error!").unwrap();
+        assert_eq!(caps.get(1).unwrap().as_str(), "error!");
+        assert_eq!(caps.get(2), None);
+    }
+
+    #[test]
+    fn nested_set_operators() {
+        let re = Regex::new("x(a&a)y").unwrap();
+        assert_eq!(re.find("zzxayzz").unwrap().range(), 2..5);
+        let re = Regex::new("<~(.*>.*)>").unwrap();
+        assert_eq!(re.find("a <b> c>").unwrap().as_str(), "<b>");
+        assert!(!Regex::new("(a|b&b)").unwrap().is_match("xbx"));
+    }
+
+    #[test]
+    fn builder_case_insensitive() {
+        let re = RegexBuilder::new("Hello.")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        assert!(re.is_match("hELLo!"));
+        let re = RegexBuilder::new("Hello.").build().unwrap();
+        assert!(!re.is_match("hELLo!"));
+    }
+
+    #[test]
+    fn builder_whole_line() {
+        let re = RegexBuilder::new("ab|abc")
+            .whole_line(true)
+            .build()
+            .unwrap();
+        assert_eq!(re.find("abc").unwrap().range(), 0..3);
+        assert!(!re.is_match("xab"));
+        assert!(!re.is_match("abcd"));
+        let re = RegexBuilder::new("a*").whole_line(true).build().unwrap();
+        let found: Vec<_> = re.find_iter("").map(|m| m.range()).collect();
+        assert_eq!(found, vec![0..0]);
+    }
+
+    #[test]
+    fn builder_whole_word() {
+        let re = RegexBuilder::new("a.*").whole_word(true).build().unwrap();
+        assert_eq!(re.find("ab-c").unwrap().as_str(), "ab-c");
+        assert_eq!(re.find("ab-cd e").unwrap().as_str(), "ab-cd e");
+        assert!(!re.is_match("ba"));
+        let re = RegexBuilder::new("a.*c").whole_word(true).build().unwrap();
+        assert_eq!(re.find("abc-cd").unwrap().as_str(), "abc");
+        assert_eq!(re.find("xac ac").unwrap().as_str(), "ac");
+        let re = RegexBuilder::new("cat").whole_word(true).build().unwrap();
+        let found: Vec<_> = re
+            .find_iter("cats cat bobcat cat")
+            .map(|m| m.start())
+            .collect();
+        assert_eq!(found, vec![5, 16]);
+    }
+
+    #[test]
+    fn escaped_operators() {
+        let re = Regex::new("a\\&b|\\~").unwrap();
+        assert_eq!(re.find("xa&b").unwrap().range(), 1..4);
+        assert_eq!(re.find("~").unwrap().range(), 0..1);
+        assert!(!re.is_match("ab"));
+    }
+
+    #[test]
+    fn why_not_searches_like_find() {
+        let re = Regex::new(".*error.*&~(.*debug.*)").unwrap();
+        assert!(re.why_not("debug: an error").is_some());
+        assert_eq!(re.why_not("an error"), None);
+        let re = RegexBuilder::new("ab").whole_word(true).build().unwrap();
+        assert_eq!(re.why_not("abc").unwrap().position, 2);
+        assert_eq!(re.why_not("abc ab"), None);
+    }
+}
@@ -21,6 +21,7 @@ use std::str::Chars;
  * The tokens types of 'thegrep' are defined below.
  */
 
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Debug, PartialEq)]
 pub enum Token {
     LParen,
@@ -29,6 +30,8 @@ pub enum Token {
     KleeneStar,
     AnyChar,
     KleenePlus,
+    IntersectionAmp,
+    ComplementTilde,
     Char(char),
 }
 
@@ -41,6 +44,7 @@ pub struct Tokenizer<'str> {
 }
 
 impl<'str> Tokenizer<'str> {
+    #[allow(mismatched_lifetime_syntaxes)]
     pub fn new(input: &'str str) -> Tokenizer {
         Tokenizer {
             chars: input.chars(),
@@ -58,24 +62,40 @@ impl<'str> Iterator for Tokenizer<'str> {
     /**
      * The `next` method ignores leading whitespace and returns the next
      * complete Some(Token) in the Tokenizer's input string or None at all.
+     * A backslash before one of the operator chars makes it a plain Char,
+     * and any other backslash is itself a plain Char.
      */
     fn next(&mut self) -> Option<Token> {
-        if let Some(c) = self.chars.next() {
-            Some(match c {
-                '(' => Token::LParen,
-                ')' => Token::RParen,
-                '|' => Token::UnionBar,
-                '*' => Token::KleeneStar,
-                '.' => Token::AnyChar,
-                '+' => Token::KleenePlus,
+        let c = self.chars.next()?;
+        Some(match c {
+            '\\' => match self.chars.clone().next() {
+                Some(escaped) if is_metachar(escaped) => {
+                    self.chars.next();
+                    Token::Char(escaped)
+                }
                 _ => Token::Char(c),
-            })
-        } else {
-            None
-        }
+            },
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '|' => Token::UnionBar,
+            '*' => Token::KleeneStar,
+            '.' => Token::AnyChar,
+            '+' => Token::KleenePlus,
+            '&' => Token::IntersectionAmp,
+            '~' => Token::ComplementTilde,
+            _ => Token::Char(c),
+        })
     }
 }
 
+/**
+ * Whether a char is an operator, which must be written with a backslash
+ * before it to be matched literally.
+ */
+pub fn is_metachar(c: char) -> bool {
+    "()|*.+&~".contains(c)
+}
+
 /**
  * Unit Tests for the `next` method.
  */
@@ -138,6 +158,38 @@ mod iterator {
         assert_eq!(tokens.next(), None);
     }
 
+    #[test]
+    fn next_intersection_amp() {
+        let mut tokens = Tokenizer::new("&");
+        assert_eq!(tokens.next(), Some(Token::IntersectionAmp));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn next_escaped() {
+        let mut tokens = Tokenizer::new("\\&\\~\\.");
+        assert_eq!(tokens.next(), Some(Token::Char('&')));
+        assert_eq!(tokens.next(), Some(Token::Char('~')));
+        assert_eq!(tokens.next(), Some(Token::Char('.')));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn next_backslash() {
+        let mut tokens = Tokenizer::new("\\a\\");
+        assert_eq!(tokens.next(), Some(Token::Char('\\')));
+        assert_eq!(tokens.next(), Some(Token::Char('a')));
+        assert_eq!(tokens.next(), Some(Token::Char('\\')));
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn next_complement_tilde() {
+        let mut tokens = Tokenizer::new("~");
+        assert_eq!(tokens.next(), Some(Token::ComplementTilde));
+        assert_eq!(tokens.next(), None);
+    }
+
     #[test]
     fn next_stress_test() {
         let mut tokens = Tokenizer::new("ab|().*+");
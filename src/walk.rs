@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/*
+ * thegrep - Tar Heel Extended Global Regular Expression Print
+ *
+ * Author(s): Alana Fiordalisi, Madison Huber
+ * ONYEN(s): fiordali, hubermm
+ *
+ * UNC Honor Pledge: I pledge I have received no unauthorized aid
+ * on this assignment. I further pledge not to distribute my solution
+ * to this code to anyone other than the course staff and partner.
+ */
+
+/*
+ * Recursive directory traversal for -r and -R, with the file name globs
+ * and ignore files that decide which files are searched.
+ */
+
+/**
+ * The options that decide which files a recursive search visits.
+ * Symlinks named on the command line are always followed, while those
+ * found inside directories are only followed with follow_links. Hidden
+ * files and directories are skipped unless hidden is set, and the
+ * .gitignore and .ignore files met along the way are honoured unless
+ * ignore_files is unset.
+ */
+#[derive(Debug, Default)]
+pub struct WalkOptions {
+    pub follow_links: bool,
+    pub hidden: bool,
+    pub ignore_files: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub exclude_dir: Vec<String>,
+}
+
+/**
+ * Visit every file to be searched under the given paths, in sorted
+ * order within each directory. Paths that are not directories are
+ * visited as they are, as long as their names pass the globs, and
+ * directories that cannot be read are visited as errors.
+ */
+pub fn walk(paths: &[String], options: &WalkOptions, visit: &mut dyn FnMut(io::Result<PathBuf>)) {
+    let mut walker = Walker {
+        options,
+        ignores: Vec::new(),
+        visited: HashSet::new(),
+    };
+    for path in paths {
+        let path = PathBuf::from(path);
+        match fs::metadata(&path) {
+            Ok(meta) if meta.is_dir() => walker.walk_dir(&path, visit),
+            _ => {
+                if walker.file_included(&path) {
+                    visit(Ok(path));
+                }
+            }
+        }
+    }
+}
+
+/**
+ * The state of a walk: the ignore files of every directory from the
+ * top of the walk down to the current one, and the directories already
+ * visited, which keeps symlink cycles from being followed forever.
+ */
+struct Walker<'a> {
+    options: &'a WalkOptions,
+    ignores: Vec<IgnoreFile>,
+    visited: HashSet<PathBuf>,
+}
+
+impl<'a> Walker<'a> {
+    fn walk_dir(&mut self, dir: &Path, visit: &mut dyn FnMut(io::Result<PathBuf>)) {
+        if let Ok(canonical) = fs::canonicalize(dir) {
+            if !self.visited.insert(canonical) {
+                return;
+            }
+        }
+
+        let mut entries: Vec<fs::DirEntry> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(Result::ok).collect(),
+            Err(e) => {
+                visit(Err(annotate(dir, e)));
+                return;
+            }
+        };
+        entries.sort_by_key(|entry| entry.file_name());
+
+        if self.options.ignore_files {
+            self.ignores.push(IgnoreFile::load(dir));
+        }
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !self.options.hidden && name.starts_with('.') {
+                continue;
+            }
+
+            let is_dir = match entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => {
+                    if !self.options.follow_links {
+                        continue;
+                    }
+                    fs::metadata(&path)
+                        .map(|meta| meta.is_dir())
+                        .unwrap_or(false)
+                }
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => false,
+            };
+            if self.ignored(&path, is_dir) {
+                continue;
+            }
+
+            if is_dir {
+                if !any_match(&self.options.exclude_dir, &name) {
+                    self.walk_dir(&path, visit);
+                }
+            } else if self.file_included(&path) {
+                visit(Ok(path));
+            }
+        }
+
+        if self.options.ignore_files {
+            self.ignores.pop();
+        }
+    }
+
+    /**
+     * Whether a file's name passes the --include and --exclude globs.
+     */
+    fn file_included(&self, path: &Path) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy(),
+            None => return true,
+        };
+        (self.options.include.is_empty() || any_match(&self.options.include, &name))
+            && !any_match(&self.options.exclude, &name)
+    }
+
+    /**
+     * Whether the ignore files in effect ignore a path. The ignore file of
+     * the deepest directory takes precedence, and within a directory the
+     * last rule that matches decides.
+     */
+    fn ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for ignore in self.ignores.iter().rev() {
+            if let Some(ignored) = ignore.decide(path, is_dir) {
+                return ignored;
+            }
+        }
+        false
+    }
+}
+
+/**
+ * Add the path to an error's message, since io errors do not say which
+ * file they are about.
+ */
+fn annotate(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+/**
+ * Whether any of the globs match the name.
+ */
+fn any_match(globs: &[String], name: &str) -> bool {
+    globs.iter().any(|glob| glob_match(glob, name))
+}
+
+/**
+ * The rules of the .gitignore and .ignore files in one directory, in the
+ * order they apply, so that .ignore rules override .gitignore ones.
+ */
+struct IgnoreFile {
+    dir: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+/**
+ * One line of an ignore file. A pattern with a slash anywhere but at its
+ * end is anchored to the directory of the ignore file and matched against
+ * the whole path from there, while any other pattern is matched against
+ * the name alone at every depth. A trailing slash only matches
+ * directories, and a leading `!` re-includes what an earlier rule ignored.
+ */
+struct IgnoreRule {
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreFile {
+    fn load(dir: &Path) -> IgnoreFile {
+        let mut rules = Vec::new();
+        for file in [".gitignore", ".ignore"].iter() {
+            if let Ok(contents) = fs::read_to_string(dir.join(file)) {
+                rules.extend(contents.lines().filter_map(IgnoreRule::parse));
+            }
+        }
+        IgnoreFile {
+            dir: dir.to_path_buf(),
+            rules,
+        }
+    }
+
+    /**
+     * Whether the last rule matching the path ignores it, or None when no
+     * rule matches it.
+     */
+    fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?;
+        let relative: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let relative = relative.join("/");
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && glob_match(&rule.pattern, if rule.anchored { &relative } else { name })
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+impl IgnoreRule {
+    fn parse(line: &str) -> Option<IgnoreRule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        Some(IgnoreRule {
+            pattern: line.trim_start_matches('/').to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
+
+/**
+ * Match a name or path against a glob. `*` matches any run of chars but
+ * `/`, `**` matches any run of chars at all, `?` matches one char but
+ * `/`, `[...]` matches one char from a set, where `[!...]` negates the
+ * set, and a backslash matches the char after it literally.
+ */
+pub fn glob_match(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_matches(&glob, &text)
+}
+
+fn glob_matches(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            // "**/" can also stand for no directories at all
+            if rest.first() == Some(&'/') && glob_matches(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|idx| glob_matches(rest, &text[idx..]))
+        }
+        Some('*') => {
+            for idx in 0..=text.len() {
+                if glob_matches(&glob[1..], &text[idx..]) {
+                    return true;
+                }
+                if idx < text.len() && text[idx] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => glob_matches(&glob[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => match class(glob) {
+            Some((len, matches)) => match text.first() {
+                Some(c) if matches(*c) => glob_matches(&glob[len..], &text[1..]),
+                _ => false,
+            },
+            None => text.first() == Some(&'[') && glob_matches(&glob[1..], &text[1..]),
+        },
+        Some('\\') if glob.len() > 1 => {
+            text.first() == Some(&glob[1]) && glob_matches(&glob[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_matches(&glob[1..], &text[1..]),
+    }
+}
+
+/**
+ * Parse the `[...]` set at the start of a glob, returning how many chars
+ * it spans and which chars it matches, or None when it is never closed.
+ */
+fn class(glob: &[char]) -> Option<(usize, impl Fn(char) -> bool)> {
+    let mut idx = 1;
+    let negated = matches!(glob.get(idx), Some('!') | Some('^'));
+    if negated {
+        idx += 1;
+    }
+    let mut ranges: Vec<(char, char)> = Vec::new();
+    let first = idx;
+    loop {
+        match glob.get(idx) {
+            None => return None,
+            Some(']') if idx > first => break,
+            Some(c) => {
+                if glob.get(idx + 1) == Some(&'-') && glob.get(idx + 2).is_some_and(|e| *e != ']') {
+                    ranges.push((*c, glob[idx + 2]));
+                    idx += 3;
+                } else {
+                    ranges.push((*c, *c));
+                    idx += 1;
+                }
+            }
+        }
+    }
+    let matches =
+        move |c: char| ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi) != negated && c != '/';
+    Some((idx + 1, matches))
+}
+
+#[cfg(test)]
+mod glob_tests {
+    use super::*;
+
+    #[test]
+    fn literal() {
+        assert!(glob_match("main.rs", "main.rs"));
+        assert!(!glob_match("main.rs", "main.rc"));
+    }
+
+    #[test]
+    fn star() {
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+    }
+
+    #[test]
+    fn double_star() {
+        assert!(glob_match("**/*.rs", "main.rs"));
+        assert!(glob_match("**/*.rs", "src/nfa/dfa.rs"));
+        assert!(glob_match("src/**", "src/nfa/dfa.rs"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+    }
+
+    #[test]
+    fn question_and_sets() {
+        assert!(glob_match("?.txt", "a.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+        assert!(glob_match("[abc].txt", "b.txt"));
+        assert!(glob_match("[a-c][!0-9]", "cx"));
+        assert!(!glob_match("[a-c][!0-9]", "c5"));
+        assert!(glob_match("[]].txt", "].txt"));
+        assert!(glob_match("[a", "[a"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(glob_match("\\*.txt", "*.txt"));
+        assert!(!glob_match("\\*.txt", "a.txt"));
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let rule = IgnoreRule::parse("/build/").unwrap();
+        assert!(rule.anchored && rule.dir_only && !rule.negated);
+        assert_eq!(rule.pattern, "build");
+        let rule = IgnoreRule::parse("!keep.log").unwrap();
+        assert!(rule.negated && !rule.anchored);
+        assert!(IgnoreRule::parse("# comment").is_none());
+        assert!(IgnoreRule::parse("").is_none());
+    }
+}
@@ -0,0 +1,669 @@
+/*
+ * Integration tests that run the thegrep binary the way a shell would.
+ */
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/**
+ * The output of a run of thegrep.
+ */
+struct Run {
+    stdout: String,
+    stderr: String,
+    code: Option<i32>,
+}
+
+/**
+ * Run thegrep with the given arguments from the given directory, feeding
+ * it the given stdin.
+ */
+fn thegrep_in(dir: &PathBuf, args: &[&str], stdin: &str) -> Run {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_thegrep"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // thegrep may exit without reading its input, such as on a bad pattern
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let output = child.wait_with_output().unwrap();
+    Run {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        code: output.status.code(),
+    }
+}
+
+/**
+ * Run thegrep with the given arguments, feeding it the given stdin.
+ */
+fn thegrep(args: &[&str], stdin: &str) -> Run {
+    thegrep_in(&std::env::temp_dir(), args, stdin)
+}
+
+/**
+ * A fresh directory for a test, filled with the given files.
+ */
+fn fixture(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("thegrep-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const LOG: &str = "info: started\nerror: disk full\ninfo: retrying\nerror: disk full\nwarn: slow\n";
+
+#[test]
+fn prints_matching_lines() {
+    let run = thegrep(&["error"], LOG);
+    assert_eq!(run.stdout, "error: disk full\nerror: disk full\n");
+}
+
+#[test]
+fn invert_match() {
+    let run = thegrep(&["-v", "error|warn"], LOG);
+    assert_eq!(run.stdout, "info: started\ninfo: retrying\n");
+}
+
+#[test]
+fn count() {
+    assert_eq!(thegrep(&["-c", "info"], LOG).stdout, "2\n");
+    assert_eq!(thegrep(&["-c", "-v", "info"], LOG).stdout, "3\n");
+    assert_eq!(thegrep(&["-c", "fatal"], LOG).stdout, "0\n");
+}
+
+#[test]
+fn count_several_files() {
+    let dir = fixture("count", &[("a.log", LOG), ("b.log", "error\n")]);
+    let run = thegrep_in(&dir, &["-c", "error", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "a.log:2\nb.log:1\n");
+    let run = thegrep_in(&dir, &["-c", "error", "b.log"], "");
+    assert_eq!(run.stdout, "1\n");
+}
+
+#[test]
+fn max_count() {
+    assert_eq!(
+        thegrep(&["-m", "1", "error"], LOG).stdout,
+        "error: disk full\n"
+    );
+    assert_eq!(
+        thegrep(&["-m", "2", "-v", "error"], LOG).stdout,
+        "info: started\ninfo: retrying\n"
+    );
+    assert_eq!(thegrep(&["-m", "0", "error"], LOG).stdout, "");
+}
+
+#[test]
+fn max_count_per_file() {
+    let dir = fixture(
+        "max_count",
+        &[("a.log", LOG), ("b.log", "error 1\nerror 2\n")],
+    );
+    let run = thegrep_in(&dir, &["-c", "-m", "1", "error", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "a.log:1\nb.log:1\n");
+    assert_eq!(run.stderr, "");
+}
+
+const WIDE: &str = "héllo wörld\nno\nwörld wörld\n";
+
+#[test]
+fn line_numbers() {
+    let run = thegrep(&["-n", "info"], LOG);
+    assert_eq!(run.stdout, "1:info: started\n3:info: retrying\n");
+    let run = thegrep(&["-n", "-v", "error|info"], LOG);
+    assert_eq!(run.stdout, "5:warn: slow\n");
+}
+
+#[test]
+fn byte_offsets() {
+    let run = thegrep(&["-b", "w.rld"], WIDE);
+    assert_eq!(run.stdout, "0:héllo wörld\n17:wörld wörld\n");
+    let run = thegrep(&["-b", "-n", "b"], "a\r\nb\r\n");
+    assert_eq!(run.stdout, "2:3:b\n");
+}
+
+#[test]
+fn columns() {
+    let run = thegrep(&["-n", "--column", "w.rld"], WIDE);
+    assert_eq!(run.stdout, "1:8:héllo wörld\n3:1:wörld wörld\n");
+    let run = thegrep(&["--column", "--column-units", "chars", "w.rld"], WIDE);
+    assert_eq!(run.stdout, "7:héllo wörld\n1:wörld wörld\n");
+}
+
+#[test]
+fn vimgrep() {
+    let dir = fixture("vimgrep", &[("a.txt", WIDE)]);
+    let run = thegrep_in(&dir, &["--vimgrep", "w.rld", "a.txt"], "");
+    assert_eq!(
+        run.stdout,
+        "a.txt:1:8:héllo wörld\na.txt:3:1:wörld wörld\na.txt:3:8:wörld wörld\n"
+    );
+    let run = thegrep(&["--vimgrep", "no"], WIDE);
+    assert_eq!(run.stdout, "(standard input):2:1:no\n");
+}
+
+#[test]
+fn filename_prefixes() {
+    let dir = fixture("prefixes", &[("a.log", LOG), ("b.log", "warn: b\n")]);
+    let run = thegrep_in(&dir, &["warn", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "a.log:warn: slow\nb.log:warn: b\n");
+    let run = thegrep_in(&dir, &["-h", "warn", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "warn: slow\nwarn: b\n");
+    let run = thegrep_in(&dir, &["-H", "-n", "warn", "b.log"], "");
+    assert_eq!(run.stdout, "b.log:1:warn: b\n");
+    let run = thegrep_in(&dir, &["-h", "-H", "warn", "b.log"], "");
+    assert_eq!(run.stdout, "b.log:warn: b\n");
+}
+
+#[test]
+fn label() {
+    let run = thegrep(&["-H", "--label", "log", "warn"], LOG);
+    assert_eq!(run.stdout, "log:warn: slow\n");
+    let run = thegrep(&["-H", "warn"], LOG);
+    assert_eq!(run.stdout, "(standard input):warn: slow\n");
+    let run = thegrep(&["-c", "-H", "--label=log", "error"], LOG);
+    assert_eq!(run.stdout, "log:2\n");
+}
+
+#[test]
+fn stdin_among_files() {
+    let dir = fixture("dash", &[("b.log", "warn: b\n")]);
+    let run = thegrep_in(&dir, &["--label", "in", "warn", "b.log", "-"], LOG);
+    assert_eq!(run.stdout, "b.log:warn: b\nin:warn: slow\n");
+    assert_eq!(run.code, Some(0));
+    let run = thegrep_in(&dir, &["-H", "warn", "-"], LOG);
+    assert_eq!(run.stdout, "(standard input):warn: slow\n");
+    let run = thegrep_in(&dir, &["-r", "warn", "-", "b.log"], LOG);
+    assert_eq!(run.stdout, "(standard input):warn: slow\nb.log:warn: b\n");
+}
+
+#[test]
+fn files_with_and_without_matches() {
+    let dir = fixture(
+        "listing",
+        &[("a.log", LOG), ("b.log", "warn: b\n"), ("c.log", "")],
+    );
+    let run = thegrep_in(&dir, &["-l", "error", "a.log", "b.log", "c.log"], "");
+    assert_eq!(run.stdout, "a.log\n");
+    let run = thegrep_in(&dir, &["-L", "error", "a.log", "b.log", "c.log"], "");
+    assert_eq!(run.stdout, "b.log\nc.log\n");
+    let run = thegrep(&["-l", "--label", "log", "error"], LOG);
+    assert_eq!(run.stdout, "log\n");
+}
+
+#[test]
+fn null_terminated_names() {
+    let dir = fixture("null", &[("a.log", LOG), ("b.log", "warn: b\n")]);
+    let run = thegrep_in(&dir, &["-l", "-Z", "warn", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "a.log\0b.log\0");
+    let run = thegrep_in(&dir, &["-Z", "warn", "a.log", "b.log"], "");
+    assert_eq!(run.stdout, "a.log\0warn: slow\nb.log\0warn: b\n");
+}
+
+#[test]
+fn recursive_search() {
+    let dir = fixture(
+        "recursive",
+        &[
+            ("a.txt", "needle a\n"),
+            ("sub/b.txt", "needle b\n"),
+            ("sub/deeper/c.log", "needle c\n"),
+            ("sub/.hidden.txt", "needle hidden\n"),
+            (".git/config", "needle git\n"),
+        ],
+    );
+    let run = thegrep_in(&dir, &["-r", "needle"], "");
+    assert_eq!(
+        run.stdout,
+        "a.txt:needle a\nsub/b.txt:needle b\nsub/deeper/c.log:needle c\n"
+    );
+    let run = thegrep_in(&dir, &["-r", "needle", "sub"], "");
+    assert_eq!(
+        run.stdout,
+        "sub/b.txt:needle b\nsub/deeper/c.log:needle c\n"
+    );
+    let run = thegrep_in(&dir, &["-r", "--hidden", "-l", "needle", "sub"], "");
+    assert_eq!(run.stdout, "sub/.hidden.txt\nsub/b.txt\nsub/deeper/c.log\n");
+}
+
+#[test]
+fn include_and_exclude() {
+    let dir = fixture(
+        "globs",
+        &[
+            ("a.txt", "needle\n"),
+            ("b.log", "needle\n"),
+            ("logs/c.log", "needle\n"),
+            ("target/d.txt", "needle\n"),
+        ],
+    );
+    let run = thegrep_in(&dir, &["-r", "-l", "--include", "*.log", "needle"], "");
+    assert_eq!(run.stdout, "b.log\nlogs/c.log\n");
+    let run = thegrep_in(&dir, &["-r", "-l", "--exclude=*.log", "needle"], "");
+    assert_eq!(run.stdout, "a.txt\ntarget/d.txt\n");
+    let run = thegrep_in(
+        &dir,
+        &[
+            "-r",
+            "-l",
+            "--exclude-dir",
+            "target",
+            "--exclude-dir",
+            "logs",
+            "needle",
+        ],
+        "",
+    );
+    assert_eq!(run.stdout, "a.txt\nb.log\n");
+}
+
+#[test]
+fn ignore_files() {
+    let dir = fixture(
+        "ignore",
+        &[
+            (".gitignore", "*.log\n/build/\n"),
+            ("app.log", "needle\n"),
+            ("keep.log", "needle\n"),
+            (".ignore", "!keep.log\n"),
+            ("build/out.txt", "needle\n"),
+            ("src/build/gen.txt", "needle\n"),
+            ("src/.gitignore", "!*.log\n"),
+            ("src/main.log", "needle\n"),
+        ],
+    );
+    let run = thegrep_in(&dir, &["-r", "-l", "needle"], "");
+    assert_eq!(run.stdout, "keep.log\nsrc/build/gen.txt\nsrc/main.log\n");
+    let run = thegrep_in(&dir, &["-r", "-l", "--no-ignore", "needle"], "");
+    assert_eq!(
+        run.stdout,
+        "app.log\nbuild/out.txt\nkeep.log\nsrc/build/gen.txt\nsrc/main.log\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() {
+    let dir = fixture(
+        "symlinks",
+        &[("real/a.txt", "needle\n"), ("top/b.txt", "needle\n")],
+    );
+    std::os::unix::fs::symlink(dir.join("real"), dir.join("top/link")).unwrap();
+    std::os::unix::fs::symlink(dir.join("top"), dir.join("top/loop")).unwrap();
+    let run = thegrep_in(&dir, &["-r", "-l", "needle", "top"], "");
+    assert_eq!(run.stdout, "top/b.txt\n");
+    let run = thegrep_in(&dir, &["-R", "-l", "needle", "top"], "");
+    assert_eq!(run.stdout, "top/b.txt\ntop/link/a.txt\n");
+    let run = thegrep_in(&dir, &["-r", "-l", "needle", "top/link"], "");
+    assert_eq!(run.stdout, "top/link/a.txt\n");
+}
+
+const LETTERS: &str = "a\nb\nc\nd\ne\nf\ng\nh\n";
+
+#[test]
+fn after_context() {
+    assert_eq!(
+        thegrep(&["-A", "1", "b|f"], LETTERS).stdout,
+        "b\nc\n--\nf\ng\n"
+    );
+    assert_eq!(thegrep(&["-A", "1", "b|d"], LETTERS).stdout, "b\nc\nd\ne\n");
+    assert_eq!(thegrep(&["-A", "1", "b|c"], LETTERS).stdout, "b\nc\nd\n");
+    assert_eq!(thegrep(&["-A", "3", "g"], LETTERS).stdout, "g\nh\n");
+}
+
+#[test]
+fn before_context() {
+    assert_eq!(
+        thegrep(&["-B", "2", "a|e"], LETTERS).stdout,
+        "a\n--\nc\nd\ne\n"
+    );
+    assert_eq!(thegrep(&["-B", "1", "c|e"], LETTERS).stdout, "b\nc\nd\ne\n");
+    assert_eq!(thegrep(&["-B", "2", "c|d"], LETTERS).stdout, "a\nb\nc\nd\n");
+}
+
+#[test]
+fn context_both_ways() {
+    assert_eq!(thegrep(&["-C", "1", "d"], LETTERS).stdout, "c\nd\ne\n");
+    assert_eq!(
+        thegrep(&["-C", "1", "-A", "0", "d"], LETTERS).stdout,
+        "c\nd\n"
+    );
+    assert_eq!(
+        thegrep(&["-C", "1", "b|g"], LETTERS).stdout,
+        "a\nb\nc\n--\nf\ng\nh\n"
+    );
+}
+
+#[test]
+fn context_separators() {
+    let run = thegrep(&["-n", "-C", "1", "d"], LETTERS);
+    assert_eq!(run.stdout, "3-c\n4:d\n5-e\n");
+    let run = thegrep(&["-b", "-H", "--label", "x", "-A", "1", "a"], LETTERS);
+    assert_eq!(run.stdout, "x:0:a\nx-2-b\n");
+}
+
+#[test]
+fn context_across_files() {
+    let dir = fixture("context", &[("one", LETTERS), ("two", LETTERS)]);
+    let run = thegrep_in(&dir, &["-h", "-A", "1", "h", "one", "two"], "");
+    assert_eq!(run.stdout, "h\n--\nh\n");
+}
+
+#[test]
+fn context_with_max_count() {
+    assert_eq!(
+        thegrep(&["-m", "1", "-A", "2", "b|c"], LETTERS).stdout,
+        "b\nc\nd\n"
+    );
+    assert_eq!(
+        thegrep(&["-m", "2", "-B", "1", "b|e"], LETTERS).stdout,
+        "a\nb\n--\nd\ne\n"
+    );
+}
+
+#[test]
+fn context_with_invert() {
+    let run = thegrep(&["-v", "-n", "-A", "1", "a|b|c|e|f|g|h"], LETTERS);
+    assert_eq!(run.stdout, "4:d\n5-e\n");
+    let run = thegrep(&["-v", "-c", "-C", "2", "a|b|c"], LETTERS);
+    assert_eq!(run.stdout, "5\n");
+}
+
+#[test]
+fn color_always() {
+    let run = thegrep(&["--color=always", "-n", "b+"], "abbc\nd\n");
+    assert_eq!(
+        run.stdout,
+        "\x1b[32m\x1b[K1\x1b[m\x1b[K\x1b[36m\x1b[K:\x1b[m\x1b[K\
+         a\x1b[01;31m\x1b[Kbb\x1b[m\x1b[Kc\n"
+    );
+    let run = thegrep(&["--colour=always", "-v", "-A", "1", "b"], "a\nb\n");
+    assert_eq!(run.stdout, "a\n\x1b[01;31m\x1b[Kb\x1b[m\x1b[K\n");
+}
+
+#[test]
+fn color_never_and_auto() {
+    assert_eq!(thegrep(&["--color=never", "b"], "abc\n").stdout, "abc\n");
+    // stdout is a pipe here, so auto does not colour
+    assert_eq!(thegrep(&["--color=auto", "b"], "abc\n").stdout, "abc\n");
+}
+
+#[test]
+fn bare_color_means_auto() {
+    // The next argument is the pattern rather than the value of --color
+    let run = thegrep(&["--color", "always"], "always\nnever\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("always\n", Some(0)));
+    assert_eq!(thegrep(&["--colour", "b"], "abc\n").stdout, "abc\n");
+    assert_eq!(thegrep(&["--color=bogus", "b"], "abc\n").code, Some(2));
+}
+
+#[test]
+fn only_matching() {
+    let run = thegrep(&["-o", "(1|2|3|4|5|7)+"], "id 12 and 345\nnone\n7\n");
+    assert_eq!(run.stdout, "12\n345\n7\n");
+    let run = thegrep(&["-o", "-n", "-b", "b+"], "abba b\nc\nb\n");
+    assert_eq!(run.stdout, "1:1:bb\n1:5:b\n3:9:b\n");
+    let run = thegrep(&["-o", "-H", "--label", "in", "-c", "b"], "abb\n");
+    assert_eq!(run.stdout, "in:1\n");
+}
+
+#[test]
+fn only_matching_empty_matches() {
+    assert_eq!(thegrep(&["-o", "x*"], "axxbx\nab\n").stdout, "xx\nx\n");
+    assert_eq!(thegrep(&["-o", "-v", "x"], "a\nb\n").stdout, "");
+}
+
+#[test]
+fn only_matching_prefixes() {
+    let dir = fixture("only", &[("one", "a1b22\n"), ("two", "3\n")]);
+    let run = thegrep_in(
+        &dir,
+        &["-o", "--column", "(1|2|3|4|5|7)+", "one", "two"],
+        "",
+    );
+    assert_eq!(run.stdout, "one:2:1\none:4:22\ntwo:1:3\n");
+    let run = thegrep(&["-o", "-C", "1", "b"], "a\nb\nc\n");
+    assert_eq!(run.stdout, "b\n");
+}
+
+#[test]
+fn line_regexp() {
+    let run = thegrep(&["-x", "ab|abc"], "abc\nab\nxab\nabcd\n");
+    assert_eq!(run.stdout, "abc\nab\n");
+    assert_eq!(thegrep(&["-x", "-c", "a*"], "\naa\nba\n").stdout, "2\n");
+    assert_eq!(thegrep(&["-x", "-v", "a"], "a\nab\n").stdout, "ab\n");
+}
+
+#[test]
+fn word_regexp() {
+    let run = thegrep(&["-w", "cat"], "cats\nbobcat\nthe cat sat\ncat_\n");
+    assert_eq!(run.stdout, "the cat sat\n");
+    let run = thegrep(&["-w", "-o", "cat"], "cats cat, bobcat cat\n");
+    assert_eq!(run.stdout, "cat\ncat\n");
+}
+
+#[test]
+fn word_regexp_retries() {
+    // the longest match is not a whole word, but a shorter one is
+    assert_eq!(thegrep(&["-w", "-o", "a.*c"], "abc-cd\n").stdout, "abc\n");
+    assert_eq!(thegrep(&["-w", "-o", "a.*"], "xa ab\n").stdout, "ab\n");
+    assert_eq!(thegrep(&["-w", "a.*"], "ba\nxyz\n").stdout, "");
+}
+
+#[test]
+fn exit_status() {
+    assert_eq!(thegrep(&["b"], "abc\n").code, Some(0));
+    assert_eq!(thegrep(&["x"], "abc\n").code, Some(1));
+    assert_eq!(thegrep(&["-v", "b"], "abc\n").code, Some(1));
+    let run = thegrep(&["(a"], "abc\n");
+    assert_eq!(run.code, Some(2));
+    assert!(run.stderr.starts_with("thegrep: "));
+}
+
+#[test]
+fn exit_status_bad_pattern_modes() {
+    for mode in &["--dot", "--gen=3", "--analyze", "--enumerate=2"] {
+        let run = thegrep(&[mode, "(a"], "");
+        assert_eq!(run.code, Some(2), "{}", mode);
+        assert!(run.stderr.starts_with("thegrep: "), "{}", mode);
+    }
+}
+
+#[test]
+fn quiet() {
+    let run = thegrep(&["-q", "b"], "abc\nb\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(0)));
+    let run = thegrep(&["-q", "-c", "x"], "abc\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(1)));
+}
+
+#[test]
+fn missing_files() {
+    let dir = fixture("missing", &[("one", "a\n"), ("two", "a\n")]);
+    let run = thegrep_in(&dir, &["a", "one", "none", "two"], "");
+    assert_eq!(run.stdout, "one:a\ntwo:a\n");
+    assert!(run.stderr.starts_with("thegrep: none: "));
+    assert_eq!(run.code, Some(2));
+
+    let run = thegrep_in(&dir, &["-s", "a", "none", "two"], "");
+    assert_eq!((run.stdout.as_str(), run.stderr.as_str()), ("two:a\n", ""));
+    assert_eq!(run.code, Some(2));
+
+    // a selected line with -q outweighs the error
+    let run = thegrep_in(&dir, &["-q", "a", "none", "two"], "");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(0)));
+}
+
+#[test]
+fn binary_files() {
+    let dir = fixture("binary", &[("bin", "abc\0\nab\n"), ("text", "ab\n")]);
+    let run = thegrep_in(&dir, &["b", "bin", "text"], "");
+    assert_eq!(run.stdout, "Binary file bin matches\ntext:ab\n");
+    assert_eq!(run.code, Some(0));
+    let run = thegrep_in(&dir, &["-c", "b", "bin"], "");
+    assert_eq!(run.stdout, "2\n");
+    let run = thegrep_in(&dir, &["x", "bin"], "");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(1)));
+}
+
+#[test]
+fn binary_files_options() {
+    let dir = fixture(
+        "binary-options",
+        &[("bin", "abc\0\nab\n"), ("text", "ab\n")],
+    );
+    let run = thegrep_in(&dir, &["-a", "-n", "ab", "bin"], "");
+    assert_eq!(run.stdout, "1:abc\0\n2:ab\n");
+    let run = thegrep_in(&dir, &["--binary-files=text", "c", "bin"], "");
+    assert_eq!(run.stdout, "abc\0\n");
+    let run = thegrep_in(&dir, &["-I", "-l", "b", "bin", "text"], "");
+    assert_eq!(run.stdout, "text\n");
+    let run = thegrep_in(&dir, &["--binary-files=without-match", "b", "bin"], "");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(1)));
+}
+
+#[test]
+fn binary_files_invalid_utf8() {
+    let dir = fixture("invalid", &[]);
+    fs::write(dir.join("latin1"), b"one\ncaf\xe9\ntwo\n").unwrap();
+    let run = thegrep_in(&dir, &["-n", "o|f", "latin1"], "");
+    assert_eq!(run.stdout, "1:one\nBinary file latin1 matches\n");
+    let run = thegrep_in(&dir, &["-a", "caf", "latin1"], "");
+    assert_eq!(run.stdout, "caf\u{fffd}\n");
+}
+
+#[test]
+fn intersection_and_complement() {
+    let lines = "debug: an error occurred\nan error occurred\nall fine\n";
+    let run = thegrep(&[".*error.*&~(.*debug.*)"], lines);
+    assert_eq!(run.stdout, "an error occurred\n");
+    let run = thegrep(&["x(a&a)y"], "zzxayzz\nxay\nxy\n");
+    assert_eq!(run.stdout, "zzxayzz\nxay\n");
+}
+
+#[test]
+fn escaped_operators() {
+    assert_eq!(thegrep(&["a\\&b"], "a&b\nab\n").stdout, "a&b\n");
+    assert_eq!(thegrep(&["-o", "\\~+"], "x~~y\n").stdout, "~~\n");
+    let run = thegrep(&["&"], "a&b\n");
+    assert_eq!(run.code, Some(2));
+}
+
+#[test]
+fn equiv() {
+    let run = thegrep(&["--equiv", "a|b", "b|a"], "");
+    assert_eq!((run.stdout.as_str(), run.code), ("equivalent\n", Some(0)));
+    let run = thegrep(&["--equiv", "a", "a|b"], "");
+    assert_eq!(
+        run.stdout,
+        "\"b\" is accepted by \"a|b\" but not by \"a\"\n"
+    );
+    assert_eq!(run.code, Some(1));
+    let run = thegrep(&["--subset", "--equiv", "a", "a|b"], "");
+    assert_eq!(run.stdout, "subset\n");
+}
+
+#[test]
+fn equiv_is_an_ordinary_pattern() {
+    let run = thegrep(&["equiv"], "equiv\nother\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("equiv\n", Some(0)));
+}
+
+#[test]
+fn exit_status_usage_errors() {
+    for args in [
+        &[][..],
+        &["--bogus", "x"],
+        &["-m", "abc", "x"],
+        &["--equiv", "a"],
+    ]
+    .iter()
+    {
+        let run = thegrep(args, "");
+        assert_eq!(run.code, Some(2), "{:?}", args);
+        assert!(run.stderr.starts_with("error: "), "{:?}", args);
+    }
+    assert_eq!(thegrep(&["--help"], "").code, Some(0));
+}
+
+#[test]
+fn trace_input_requires_dot() {
+    let run = thegrep(&["--trace-input", "ab", "ab"], "ab\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(2)));
+    let run = thegrep(&["--dot", "--trace-input", "ab", "ab"], "");
+    assert!(run.stdout.starts_with("digraph"));
+}
+
+#[test]
+fn gen_lengths() {
+    let run = thegrep(&["--gen", "20", "--length", "2..3", "(a|b)+"], "");
+    assert_eq!(run.stdout.lines().count(), 20);
+    assert!(run.stdout.lines().all(|line| line.len() == 2));
+    let run = thegrep(&["--gen", "20", "--length", "3..=3", "(a|b)+"], "");
+    assert!(run.stdout.lines().all(|line| line.len() == 3));
+    let run = thegrep(&["--gen", "8", "--length", "5", ".*"], "");
+    let distinct: std::collections::HashSet<&str> = run.stdout.lines().collect();
+    assert!(distinct.len() > 1, "{:?}", run.stdout);
+    let run = thegrep(
+        &["--count-lengths", "1..3", "(a|b)+", "--alphabet", "ab"],
+        "",
+    );
+    assert_eq!(run.stdout, "alphabet\tab\nlength\tcount\n1\t2\n2\t4\n");
+}
+
+#[test]
+fn length_requires_gen() {
+    let run = thegrep(&["--length", "2..=3", "(a|b)+"], "ab\n");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(2)));
+    assert!(run.stderr.starts_with("error: "));
+}
+
+#[test]
+fn why_not_agrees_with_grep() {
+    let pattern = ".*error.*&~(.*debug.*)";
+    for line in &["debug: an error", "an error"] {
+        let grep = thegrep(&[pattern], &format!("{}\n", line));
+        let why_not = thegrep(&["--why-not", line, pattern], "");
+        assert_eq!(grep.code, why_not.code, "{:?}", line);
+    }
+    let run = thegrep(&["-x", "--why-not", "abc", "ab"], "");
+    assert_eq!(run.code, Some(1));
+    assert!(run
+        .stdout
+        .ends_with("expected the end of the line, found 'c'\n"));
+}
+
+#[test]
+fn coverage_agrees_with_grep() {
+    let pattern = ".*error.*&~(.*debug.*)|x";
+    let run = thegrep(&["--coverage", pattern], "debug: an error\nan error\n");
+    assert!(run.stdout.starts_with("2 lines, 1 matched\n"));
+    let run = thegrep(&["-w", "--coverage", "ab|b"], "xab b\n");
+    assert_eq!(
+        run.stdout,
+        "1 lines, 1 matched\n   ab|b\n0  ^^  never matched\n1     ^\n"
+    );
+}
+
+#[test]
+fn count_lengths_alphabet() {
+    let run = thegrep(&["--count-lengths", "0..=2", ".*"], "");
+    let alphabet: String = ('0'..='9').chain('A'..='Z').chain('a'..='z').collect();
+    let expected = format!(
+        "alphabet\t{}\nlength\tcount\n0\t1\n1\t62\n2\t3844\n",
+        alphabet
+    );
+    assert_eq!(run.stdout, expected);
+    let run = thegrep(&["--count-lengths", "0..=18446744073709551615", "a"], "");
+    assert_eq!((run.stdout.as_str(), run.code), ("", Some(2)));
+}